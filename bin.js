@@ -7,6 +7,11 @@ const args = process.argv.slice(2);
 try {
   run(args);
 } catch (e) {
+  if (e.code === "Cancelled") {
+    // Interrupted by Ctrl-C or SIGTERM
+    console.error(e.message);
+    process.exit(130);
+  }
   console.error(e);
   process.exit(1);
 }
@@ -1,16 +1,37 @@
-use crate::{commands::FOLDER_VERSION_BASE, os};
-use anyhow::{bail, Result};
-use reqwest::StatusCode;
-use serde::Deserialize;
+use crate::{
+    cache::ArchiveCache,
+    commands::{
+        build_dir_in, CACHE_DIR, FOLDER_VERSION_BASE, REMOTE_VERSIONS_CACHE_PATH, TRUSTED_KEYS_DIR,
+    },
+    config::{file_url_path, Config},
+    download::{download_file, NotFound, RetryPolicy},
+    os,
+    remote_versions::{unix_now, CachedReleases, CACHE_FORMAT},
+    utils::format_utc,
+    verify::{self, CHECKSUMS_FILE_NAME, SIGNATURE_FILE_NAME},
+    version::BunVersion,
+};
+use anyhow::{anyhow, bail, Result};
+use pgp::composed::SignedPublicKey;
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH, LINK},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use tokio::{
-    fs::{self, create_dir_all, File},
-    io::{AsyncWriteExt, BufWriter},
+    fs::{self, create_dir_all},
     process::Command,
 };
 
-const BUN_GITHUB_TAGS_URL: &str = "https://api.github.com/repos/oven-sh/bun/tags";
+const BUN_GITHUB_RELEASES_PATH: &str = "/repos/oven-sh/bun/releases";
 pub const BUN_BIN_NAME: &str = "bun";
+/// Path to an extra armored public key to trust for release signatures.
+const TRUSTED_KEY_ENV_VAR: &str = "BUM_TRUSTED_KEY";
+/// Tokens sent to the GitHub API, which raise its rate limit from 60 to
+/// 5000 requests per hour. The first one that's set wins.
+const GITHUB_TOKEN_ENV_VARS: [&str; 2] = ["GITHUB_TOKEN", "GH_TOKEN"];
 
 fn get_bun_bin_name() -> &'static str {
     #[cfg(target_os = "windows")]
@@ -41,79 +62,892 @@ pub async fn get_active_version() -> String {
     stdout.trim().to_string()
 }
 
+/// How many releases GitHub returns per page at most.
+const RELEASES_PER_PAGE: u32 = 100;
+/// Stops following `Link` headers that never end.
+const MAX_RELEASE_PAGES: usize = 50;
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
 #[derive(Deserialize)]
-struct Tag {
+struct GithubAsset {
     name: String,
 }
 
-pub async fn get_github_tags() -> Result<Vec<String>> {
-    let client = reqwest::Client::builder()
-        .user_agent("bum-version-manager-app")
-        .build()?;
+/// A published Bun release.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: BunVersion,
+    /// When it was published, as an RFC 3339 timestamp
+    #[serde(default)]
+    pub published_at: Option<String>,
+    /// The file names of its assets, e.g. `bun-linux-x64.zip`
+    #[serde(default)]
+    pub assets: Vec<String>,
+}
+
+impl Release {
+    /// The day it was published, e.g. `2024-07-13`.
+    pub fn published_on(&self) -> Option<&str> {
+        self.published_at.as_deref()?.get(..10)
+    }
+
+    /// Whether there's a zip for `architecture` (like `linux-x64-musl`).
+    /// Releases whose assets aren't known are assumed to have one.
+    pub fn has_build_for(&self, architecture: &str) -> bool {
+        let asset_name = format!("bun-{}.zip", architecture);
+        self.assets.is_empty() || self.assets.contains(&asset_name)
+    }
+
+    /// The architectures it has a zip for, e.g. `linux-x64-musl`. Profile
+    /// builds, which come with debug symbols, aren't counted.
+    pub fn platforms(&self) -> Vec<&str> {
+        self.assets
+            .iter()
+            .filter_map(|asset| asset.strip_prefix("bun-")?.strip_suffix(".zip"))
+            .filter(|platform| !platform.ends_with("-profile"))
+            .collect()
+    }
+}
+
+/// The pages of releases fetched from the GitHub API.
+enum FetchedReleases {
+    /// The first page still has the `ETag` that was sent
+    NotModified,
+    Releases {
+        releases: Vec<Release>,
+        etag: Option<String>,
+        /// There were more pages than `MAX_RELEASE_PAGES`
+        truncated: bool,
+    },
+}
+
+/// Everything installing a release depends on besides the version: the
+/// config, the platform and the files under `~/.bum` it reads.
+pub struct InstallContext {
+    pub config: Config,
+    /// The build to install, e.g. `linux-x64-musl`
+    pub architecture: String,
+    /// The armored keys built into bum
+    pub embedded_keys: &'static [&'static str],
+    pub trusted_keys_dir: PathBuf,
+    pub extra_key_path: Option<PathBuf>,
+    pub cache_dir: PathBuf,
+    pub remote_versions_cache_path: PathBuf,
+    pub github_token: Option<String>,
+}
+
+impl InstallContext {
+    pub fn load() -> Result<Self> {
+        Ok(InstallContext {
+            config: Config::load()?,
+            architecture: os::get_architecture(),
+            embedded_keys: verify::EMBEDDED_RELEASE_KEYS,
+            trusted_keys_dir: TRUSTED_KEYS_DIR.to_path_buf(),
+            extra_key_path: std::env::var_os(TRUSTED_KEY_ENV_VAR).map(PathBuf::from),
+            cache_dir: CACHE_DIR.to_path_buf(),
+            remote_versions_cache_path: REMOTE_VERSIONS_CACHE_PATH.to_path_buf(),
+            github_token: github_token(),
+        })
+    }
+
+    fn trusted_keys(&self) -> Result<Vec<SignedPublicKey>> {
+        let keys = verify::load_trusted_keys(
+            self.embedded_keys,
+            &self.trusted_keys_dir,
+            self.extra_key_path.as_deref(),
+        )?;
+
+        // Checksums without a checked signature prove nothing
+        if keys.is_empty() {
+            bail!(
+                "No trusted release keys to check the signature of {CHECKSUMS_FILE_NAME} with, refusing to install. Add one to {} or set {TRUSTED_KEY_ENV_VAR}",
+                self.trusted_keys_dir.display()
+            );
+        }
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+impl InstallContext {
+    /// Installs the `linux-x64` build from `mirror`, which also serves the
+    /// release list, and keeps bum's own files in `dir`.
+    pub fn for_mirror(mirror: &str, dir: &Path, extra_key_path: Option<&Path>) -> Self {
+        InstallContext {
+            config: Config {
+                download_mirror: mirror.to_string(),
+                github_api_url: mirror.to_string(),
+                download_retries: 0,
+                cache_size_limit: 0,
+                remote_versions_ttl: std::time::Duration::ZERO,
+            },
+            architecture: "linux-x64".to_string(),
+            embedded_keys: verify::EMBEDDED_RELEASE_KEYS,
+            trusted_keys_dir: dir.join("trusted-keys"),
+            extra_key_path: extra_key_path.map(Path::to_path_buf),
+            cache_dir: dir.join("cache"),
+            remote_versions_cache_path: dir.join("remote-versions.json"),
+            github_token: None,
+        }
+    }
+}
+
+/// Fetches every published Bun release, newest first. Drafts and releases
+/// whose tag isn't a Bun version (e.g. `canary`) are skipped.
+///
+/// The list from the GitHub API is cached, see `github_releases`. With
+/// `refresh`, it's fetched again regardless.
+pub async fn get_releases(refresh: bool) -> Result<Vec<Release>> {
+    get_releases_with(&InstallContext::load()?, refresh).await
+}
+
+async fn get_releases_with(ctx: &InstallContext, refresh: bool) -> Result<Vec<Release>> {
+    let mut releases = match file_url_path(&ctx.config.github_api_url) {
+        Some(releases_dir) => releases_in(&releases_dir).await?,
+        None => {
+            github_releases(
+                &ctx.config,
+                &ctx.remote_versions_cache_path,
+                ctx.github_token.as_deref(),
+                refresh,
+            )
+            .await?
+        }
+    };
 
-    let response = client.get(BUN_GITHUB_TAGS_URL).send().await?;
+    releases.sort_by(|a, b| b.version.cmp(&a.version));
+    releases.dedup_by(|a, b| a.version == b.version);
 
-    Ok(response
-        .json::<Vec<Tag>>()
+    Ok(releases)
+}
+
+/// The released Bun versions, newest first.
+pub async fn get_remote_versions(ctx: &InstallContext, refresh: bool) -> Result<Vec<BunVersion>> {
+    Ok(get_releases_with(ctx, refresh)
         .await?
         .into_iter()
-        .filter_map(|tag| {
-            if tag.name.starts_with("bun-") {
-                Some(tag.name.replace("bun-", ""))
-            } else {
-                None
+        .map(|release| release.version)
+        .collect())
+}
+
+fn github_token() -> Option<String> {
+    GITHUB_TOKEN_ENV_VARS
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|token| token.trim().to_string())
+        .find(|token| !token.is_empty())
+}
+
+/// The releases from the GitHub API, cached at `cache_path`. The cached list
+/// is used as is for `remote_versions_ttl`. After that, the API is asked
+/// with the `ETag` of the list, and a 304 (which doesn't count against the
+/// rate limit) keeps using it for another `remote_versions_ttl`.
+async fn github_releases(
+    config: &Config,
+    cache_path: &Path,
+    token: Option<&str>,
+    refresh: bool,
+) -> Result<Vec<Release>> {
+    let cached = CachedReleases::read(cache_path, &config.github_api_url);
+    let now = unix_now();
+
+    if let Some(cached) = &cached {
+        if !refresh && cached.is_fresh(config.remote_versions_ttl, now) {
+            return Ok(cached.releases.clone());
+        }
+    }
+
+    let client = github_client()?;
+    let etag = cached
+        .as_ref()
+        .filter(|_| !refresh)
+        .and_then(|cached| cached.etag.as_deref());
+
+    let updated = match (
+        fetch_releases(&client, &config.github_api_url, token, etag).await?,
+        cached,
+    ) {
+        (FetchedReleases::NotModified, Some(cached)) => CachedReleases {
+            fetched_at: now,
+            ..cached
+        },
+        (FetchedReleases::NotModified, None) => {
+            bail!("GitHub answered 304 Not Modified without a cached release list")
+        }
+        (
+            FetchedReleases::Releases {
+                releases,
+                etag,
+                truncated,
+            },
+            _,
+        ) => {
+            if truncated {
+                eprintln!(
+                    "Warning: stopped after {} pages of Bun releases, older releases are left out",
+                    MAX_RELEASE_PAGES
+                );
             }
+            CachedReleases {
+                format: CACHE_FORMAT,
+                api_url: config.github_api_url.clone(),
+                fetched_at: now,
+                etag,
+                releases,
+            }
+        }
+    };
+
+    if let Err(e) = updated.write(cache_path) {
+        eprintln!("Warning: failed to cache the list of Bun releases: {e}");
+    }
+
+    Ok(updated.releases)
+}
+
+/// Fetches the releases from the GitHub API at `api_url`, page by page. With
+/// `etag`, the first page is requested with `If-None-Match`, and nothing more
+/// is fetched when it's unchanged: new releases show up on the first page.
+///
+/// `token` only goes to `api_url` itself, never to where a `Link` header
+/// points elsewhere.
+async fn fetch_releases(
+    client: &reqwest::Client,
+    api_url: &str,
+    token: Option<&str>,
+    etag: Option<&str>,
+) -> Result<FetchedReleases> {
+    let mut releases = Vec::new();
+    let mut first_page_etag = None;
+    let mut next_url = Some(format!(
+        "{}{}?per_page={}",
+        api_url, BUN_GITHUB_RELEASES_PATH, RELEASES_PER_PAGE
+    ));
+
+    for page_number in 0..MAX_RELEASE_PAGES {
+        let Some(url) = next_url.take() else {
+            break;
+        };
+        let is_first_page = page_number == 0;
+
+        let mut request = client.get(&url);
+        if let Some(token) = token.filter(|_| same_origin(&url, api_url)) {
+            request = request.bearer_auth(token);
+        }
+        if let Some(etag) = etag.filter(|_| is_first_page) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED && is_first_page {
+            return Ok(FetchedReleases::NotModified);
+        }
+        if !response.status().is_success() {
+            return Err(github_api_error(&response, token.is_some()));
+        }
+
+        if is_first_page {
+            first_page_etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(|etag| etag.to_string());
+        }
+        next_url = response
+            .headers()
+            .get(LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(next_page_url);
+
+        let page = response.json::<Vec<GithubRelease>>().await?;
+        releases.extend(
+            page.into_iter()
+                .filter(|release| !release.draft)
+                .filter_map(release_of),
+        );
+    }
+
+    Ok(FetchedReleases::Releases {
+        releases,
+        etag: first_page_etag,
+        truncated: next_url.is_some(),
+    })
+}
+
+/// Whether both URLs have the same scheme, host and port.
+fn same_origin(url: &str, other: &str) -> bool {
+    match (reqwest::Url::parse(url), reqwest::Url::parse(other)) {
+        (Ok(url), Ok(other)) => url.origin() == other.origin(),
+        _ => false,
+    }
+}
+
+/// Explains a failed GitHub API response, in particular running into the
+/// rate limit, which GitHub answers with a 403 or 429.
+fn github_api_error(response: &reqwest::Response, has_token: bool) -> anyhow::Error {
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+
+    if status == StatusCode::UNAUTHORIZED && has_token {
+        return anyhow!(
+            "GitHub rejected the token in {} ({}), check that it's valid and hasn't expired",
+            GITHUB_TOKEN_ENV_VARS.join("/"),
+            status
+        );
+    }
+
+    let rate_limited = match status {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => header("x-ratelimit-remaining").as_deref() == Some("0"),
+        _ => false,
+    };
+    if !rate_limited {
+        return anyhow!("Failed to fetch the Bun releases: {}", status);
+    }
+
+    let mut message = match header("x-ratelimit-limit") {
+        Some(limit) => format!(
+            "GitHub API rate limit of {} requests per hour exceeded",
+            limit
+        ),
+        None => "GitHub API rate limit exceeded".to_string(),
+    };
+    if let Some(reset) = header("x-ratelimit-reset").and_then(|reset| reset.parse().ok()) {
+        message.push_str(&format!(", it resets at {}", format_utc(reset)));
+    } else if let Some(retry_after) = header("retry-after") {
+        message.push_str(&format!(", try again in {}s", retry_after));
+    }
+    if !has_token {
+        message.push_str(&format!(
+            ". Set {} to a GitHub token to raise the limit",
+            GITHUB_TOKEN_ENV_VARS.join(" or ")
+        ));
+    }
+
+    anyhow!(message)
+}
+
+fn github_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent("bum-version-manager-app")
+        .build()?)
+}
+
+fn release_of(release: GithubRelease) -> Option<Release> {
+    Some(Release {
+        version: release_version(&release.tag_name)?,
+        published_at: release.published_at,
+        assets: release.assets.into_iter().map(|asset| asset.name).collect(),
+    })
+}
+
+/// The version of a Bun release tag like `bun-v1.1.20`. Other tags, like
+/// `canary`, aren't releases.
+fn release_version(tag: &str) -> Option<BunVersion> {
+    if !tag.starts_with("bun-") {
+        return None;
+    }
+    tag.parse().ok()
+}
+
+/// The `rel="next"` URL of a `Link` header, like GitHub sends with every
+/// page but the last one.
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"");
+        is_next.then(|| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
         })
-        .collect())
+    })
 }
 
-pub async fn download_version_to(version: &str, to_path: &Path) -> Result<PathBuf> {
-    let client = reqwest::Client::new();
+/// The releases in a local mirror, whose directories are named after the
+/// release tags and hold the assets.
+async fn releases_in(releases_dir: &Path) -> Result<Vec<Release>> {
+    let mut releases = Vec::new();
+
+    for name in release_dirs_in(releases_dir).await? {
+        let Some(version) = release_version(&name) else {
+            continue;
+        };
+        let mut assets = Vec::new();
+        let mut entries = fs::read_dir(releases_dir.join(&name)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(asset) = entry.file_name().to_str() {
+                assets.push(asset.to_string());
+            }
+        }
+        assets.sort();
+
+        releases.push(Release {
+            version,
+            published_at: None,
+            assets,
+        });
+    }
 
-    let arch = os::get_architecture();
+    Ok(releases)
+}
 
-    let url = format!(
-        "https://github.com/oven-sh/bun/releases/download/bun-v{}/bun-{}.zip",
-        version, arch
-    );
+/// Names of the release directories in a local mirror, which are named after
+/// the release tags.
+async fn release_dirs_in(releases_dir: &Path) -> Result<Vec<String>> {
+    let mut entries = fs::read_dir(releases_dir).await.map_err(|e| {
+        anyhow!(
+            "Failed to read the Bun releases in {}: {}",
+            releases_dir.display(),
+            e
+        )
+    })?;
+
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+fn release_asset_url(config: &Config, version: &str, asset_name: &str) -> String {
+    format!("{}/bun-v{}/{}", config.download_mirror, version, asset_name)
+}
+
+async fn get_release_file(
+    client: &reqwest::Client,
+    config: &Config,
+    version: &str,
+    file_name: &str,
+) -> Result<String> {
+    let url = release_asset_url(config, version, file_name);
+
+    if let Some(path) = file_url_path(&url) {
+        return match fs::read_to_string(&path).await {
+            Ok(contents) => Ok(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                bail!("Bun v{version} has no {file_name}, refusing to install a binary that can't be verified")
+            }
+            Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+        };
+    }
 
     let response = client.get(url).send().await?;
 
     match response.status() {
-        StatusCode::OK => {
-            let file = File::create(to_path).await?;
-            let mut writer = BufWriter::new(file);
+        StatusCode::OK => Ok(response.text().await?),
+        StatusCode::NOT_FOUND => {
+            bail!("Bun v{version} has no {file_name}, refusing to install a binary that can't be verified")
+        }
+        e => {
+            bail!("Failed to download {file_name} of Bun v{version}: {e}");
+        }
+    }
+}
+
+/// Downloads the checksums of a release and checks they're signed by a
+/// trusted key, so a compromised download host can't just publish matching
+/// checksums for a tampered binary.
+async fn get_checksums(
+    client: &reqwest::Client,
+    config: &Config,
+    keys: &[SignedPublicKey],
+    version: &str,
+) -> Result<String> {
+    let checksums = get_release_file(client, config, version, CHECKSUMS_FILE_NAME).await?;
+    let signature = get_release_file(client, config, version, SIGNATURE_FILE_NAME).await?;
+
+    verify::verify_checksums_signature(&checksums, &signature, keys).map_err(|e| {
+        anyhow!("Signature check of {CHECKSUMS_FILE_NAME} for Bun v{version} failed: {e}. Refusing to install it")
+    })
+}
+
+/// Fails when `version` wasn't released, or its release has no zip for the
+/// architecture of `ctx`. The cached release list is used when it has the
+/// version, otherwise just that one release is looked up.
+async fn check_release_has_build(ctx: &InstallContext, version: &BunVersion) -> Result<()> {
+    let release = match find_release(ctx, version).await? {
+        Some(release) => release,
+        None => bail!("Version \"{version}\" doesn't exist"),
+    };
+
+    missing_build_error(&release, &ctx.architecture).map_or(Ok(()), Err)
+}
 
-            // Read the entire response body into a Vec<u8>
-            let bytes = response.bytes().await?;
+async fn find_release(ctx: &InstallContext, version: &BunVersion) -> Result<Option<Release>> {
+    let is_version = |release: &Release| &release.version == version;
 
-            // Write the bytes to the local file
-            writer.write_all(&bytes).await?;
+    if let Some(releases_dir) = file_url_path(&ctx.config.github_api_url) {
+        return Ok(releases_in(&releases_dir)
+            .await?
+            .into_iter()
+            .find(is_version));
+    }
+
+    let cached = CachedReleases::read(&ctx.remote_versions_cache_path, &ctx.config.github_api_url);
+    if let Some(release) = cached.and_then(|cached| cached.releases.into_iter().find(is_version)) {
+        return Ok(Some(release));
+    }
 
-            writer.flush().await?;
+    let url = format!(
+        "{}{}/tags/bun-v{}",
+        ctx.config.github_api_url, BUN_GITHUB_RELEASES_PATH, version
+    );
+    let mut request = github_client()?.get(url);
+    if let Some(token) = &ctx.github_token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => Ok(release_of(response.json().await?)),
+        _ => Err(github_api_error(&response, ctx.github_token.is_some())),
+    }
+}
+
+fn missing_build_error(release: &Release, architecture: &str) -> Option<anyhow::Error> {
+    if release.has_build_for(architecture) {
+        return None;
+    }
+
+    let mut platforms = release.platforms();
+    platforms.sort();
+    let available = if platforms.is_empty() {
+        "none".to_string()
+    } else {
+        platforms.join(", ")
+    };
+
+    Some(anyhow!(
+        "Bun v{} exists but has no build for this platform ({}). Available platforms: {}",
+        release.version,
+        architecture,
+        available
+    ))
+}
+
+/// Downloads, verifies and installs `version` into `versions_dir`, and
+/// returns the path of its `bun` binary.
+///
+/// Everything happens in `<versions_dir>/.staging` first, and only a checked
+/// binary is moved into its version directory, so an interrupted install
+/// never looks installed.
+pub async fn install_release(
+    ctx: &InstallContext,
+    version: &BunVersion,
+    versions_dir: &Path,
+) -> Result<PathBuf> {
+    let client = reqwest::Client::new();
+    let config = &ctx.config;
+    let version_name = version.to_string();
+    let version_str = version_name.as_str();
+
+    let arch = &ctx.architecture;
+    let asset_name = format!("bun-{}.zip", arch);
+    if arch.ends_with("-baseline") {
+        eprintln!("Using the baseline build of Bun, for CPUs without AVX2");
+    }
+    let url = release_asset_url(config, version_str, &asset_name);
+
+    // Everything that can rule the install out is checked before the zip is
+    // downloaded, which is by far the slowest part
+    let keys = ctx.trusted_keys()?;
+    // Otherwise the download would 404 as if the version didn't exist
+    check_release_has_build(ctx, version).await?;
+    let checksums = get_checksums(&client, config, &keys, version_str).await?;
+    let digest = verify::expected_checksum(&checksums, &asset_name)?;
+
+    let staging_root = staging_root_in(versions_dir);
+    create_dir_all(&staging_root).await?;
+    // Named after the release, so an interrupted download resumes next time
+    let zip_path = staging_root.join(format!("bun-v{}-{}", version, asset_name));
+
+    let cache = ArchiveCache::new(&ctx.cache_dir, config.cache_size_limit);
+    let cached = cache.get(&digest);
+
+    if let Some(cached) = &cached {
+        eprintln!("Using the cached archive of Bun v{version}");
+        fs::copy(cached, &zip_path).await?;
+    } else if let Some(path) = file_url_path(&url) {
+        if !path.is_file() {
+            bail!(
+                "Version \"{version}\" doesn't exist in {}",
+                config.download_mirror
+            );
         }
-        StatusCode::NOT_FOUND => {
-            bail!("Version \"{version}\" doesn't exist");
+        fs::copy(&path, &zip_path).await?;
+    } else {
+        let policy = RetryPolicy::new(config.download_retries);
+        if let Err(e) = download_file(&client, &url, &zip_path, &policy).await {
+            if e.is::<NotFound>() {
+                bail!("Version \"{version}\" doesn't exist");
+            }
+            return Err(e);
         }
-        e => {
-            bail!("HTTP request was not successful: {e}");
+    }
+
+    eprintln!("Verifying checksum...");
+    if let Err(e) = verify::verify_checksum(&zip_path, &checksums, &asset_name) {
+        fs::remove_file(&zip_path).await?;
+        return Err(e);
+    }
+
+    if cached.is_none() {
+        if let Err(e) = cache.put(&zip_path, &digest, version_str, &asset_name) {
+            eprintln!("Warning: failed to cache the archive of Bun v{version}: {e}");
         }
     }
 
-    extract_bun_bin_of_zip(&to_path, &FOLDER_VERSION_BASE).await
+    let build_dir = build_dir_in(
+        versions_dir,
+        version,
+        os::build_of_asset(&asset_name).as_deref(),
+    );
+    let staging_dir = staging_root.join(format!("{}-{}", version, std::process::id()));
+
+    let installed = with_staging_dir(&staging_dir, async {
+        let staged_bun_path = extract_bun_bin_of_zip(&zip_path, &staging_dir).await?;
+
+        let reported = read_binary_version(&staged_bun_path).await.map_err(|e| {
+            anyhow!("{e}. The downloaded build of Bun v{version} doesn't run on this system")
+        })?;
+        if !reported.same_release(version) {
+            bail!("The downloaded binary is Bun v{reported} instead of v{version}, refusing to install it");
+        }
+
+        move_into_place(&staging_dir, &staged_bun_path, &build_dir).await
+    })
+    .await;
+
+    // Also when the install failed or was interrupted halfway through
+    let _ = fs::remove_file(&zip_path).await;
+
+    installed
 }
 
-// TODO: Refactor this function when zip crate has async: https://github.com/zip-rs/zip2/pull/73
-async fn extract_bun_bin_of_zip(zip_file_path: &Path, output_dir: &Path) -> Result<PathBuf> {
-    // Extract the version from the ZIP file name (excluding ".zip" suffix)
-    let version = Path::new(zip_file_path)
-        .file_stem()
-        .and_then(|stem| stem.to_str())
-        .expect("Invalid ZIP file path");
+/// Returned when bum gets interrupted (Ctrl-C, SIGTERM) during an install,
+/// once everything it staged is removed. How to exit is up to the caller.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Installation interrupted, nothing was installed")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Where installs are prepared. It's inside the versions directory so the
+/// final rename never crosses file systems.
+fn staging_root_in(versions_dir: &Path) -> PathBuf {
+    versions_dir.join(".staging")
+}
+
+/// Runs `install`, and removes `staging_dir` afterwards, also when it fails
+/// or when bum gets interrupted (Ctrl-C, SIGTERM) halfway through.
+async fn with_staging_dir<T>(
+    staging_dir: &Path,
+    install: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    let result = tokio::select! {
+        result = install => Some(result),
+        _ = interrupted() => None,
+    };
+
+    let _ = fs::remove_dir_all(staging_dir).await;
+
+    result.unwrap_or_else(|| Err(Interrupted.into()))
+}
+
+async fn interrupted() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => return,
+                _ = terminate.recv() => return,
+            }
+        }
+    }
+
+    if tokio::signal::ctrl_c().await.is_err() {
+        // Without signal handling, there's nothing to wait for
+        std::future::pending::<()>().await;
+    }
+}
 
-    let output_dir = output_dir.join(version);
+/// Moves a checked binary from the staging directory to `build_dir`. Either
+/// step is a single rename, so `bun` is only ever there complete.
+async fn move_into_place(
+    staging_dir: &Path,
+    staged_bun_path: &Path,
+    build_dir: &Path,
+) -> Result<PathBuf> {
+    let bun_path = build_dir.join(get_bun_bin_name());
 
-    println!("Extracting zip file...");
+    if fs::metadata(build_dir).await.is_err() {
+        if let Some(parent) = build_dir.parent() {
+            create_dir_all(parent).await?;
+        }
+        fs::rename(staging_dir, build_dir).await?;
+    } else {
+        // e.g. the version directory that already holds the musl build
+        fs::rename(staged_bun_path, &bun_path).await?;
+    }
+
+    Ok(bun_path)
+}
+
+/// Installs Bun from a release zip on disk, e.g. one copied by hand to a
+/// host without network access. The binary has to report `version`, and
+/// without one, the version is whatever it reports. A `SHASUMS256.txt` (and its signature) next to the zip
+/// is checked like the one of a download.
+pub async fn install_from_file(
+    zip_path: &Path,
+    version: Option<&str>,
+) -> Result<(BunVersion, PathBuf)> {
+    install_from_file_in(zip_path, version, &FOLDER_VERSION_BASE).await
+}
+
+async fn install_from_file_in(
+    zip_path: &Path,
+    version: Option<&str>,
+    versions_dir: &Path,
+) -> Result<(BunVersion, PathBuf)> {
+    if !zip_path.is_file() {
+        bail!("{} doesn't exist", zip_path.display());
+    }
+    let version: Option<BunVersion> = version.map(str::parse).transpose()?;
+
+    verify_local_zip(zip_path).await?;
+
+    // Release zips are named after their build, e.g. bun-linux-x64-musl.zip
+    let file_name = zip_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let build = if file_name.starts_with("bun-") {
+        os::build_of_asset(file_name)
+    } else {
+        os::get_build()
+    };
+
+    let staging_dir =
+        staging_root_in(versions_dir).join(format!("from-file-{}", std::process::id()));
+
+    with_staging_dir(&staging_dir, async {
+        let staged_bun_path = extract_bun_bin_of_zip(zip_path, &staging_dir).await?;
+        // Run it either way, to not install a build for another system
+        let reported = read_binary_version(&staged_bun_path).await.map_err(|e| {
+            anyhow!(
+                "{e}. The Bun in {} doesn't run on this system",
+                zip_path.display()
+            )
+        })?;
+        let version = match version {
+            Some(version) if !reported.same_release(&version) => bail!(
+                "{} holds Bun v{} instead of v{}, refusing to install it",
+                zip_path.display(),
+                reported,
+                version
+            ),
+            Some(version) => version,
+            None => reported,
+        };
+
+        let build_dir = build_dir_in(versions_dir, &version, build.as_deref());
+        if fs::metadata(build_dir.join(get_bun_bin_name()))
+            .await
+            .is_ok()
+        {
+            bail!("Bun v{} is already installed", version);
+        }
+
+        let bun_path = move_into_place(&staging_dir, &staged_bun_path, &build_dir).await?;
+        Ok((version, bun_path))
+    })
+    .await
+}
+
+async fn verify_local_zip(zip_path: &Path) -> Result<()> {
+    let checksums_path = zip_path.with_file_name(CHECKSUMS_FILE_NAME);
+    if !checksums_path.is_file() {
+        eprintln!(
+            "No {} next to {}, skipping checksum verification",
+            CHECKSUMS_FILE_NAME,
+            zip_path.display()
+        );
+        return Ok(());
+    }
+
+    eprintln!("Verifying checksum...");
+    let mut checksums = fs::read_to_string(&checksums_path).await?;
+
+    let signature_path = zip_path.with_file_name(SIGNATURE_FILE_NAME);
+    if signature_path.is_file() {
+        let keys = InstallContext::load()?.trusted_keys()?;
+        let signature = fs::read_to_string(&signature_path).await?;
+        checksums =
+            verify::verify_checksums_signature(&checksums, &signature, &keys).map_err(|e| {
+                anyhow!(
+                    "Signature check of {} failed: {}. Refusing to install it",
+                    checksums_path.display(),
+                    e
+                )
+            })?;
+    }
+
+    let asset_name = zip_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid zip file path {}", zip_path.display()))?;
+    verify::verify_checksum(zip_path, &checksums, asset_name)
+}
+
+async fn read_binary_version(bun_path: &Path) -> Result<BunVersion> {
+    let output = Command::new(bun_path)
+        .arg("--version")
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run {}: {}", bun_path.display(), e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().parse() {
+        Ok(version) if output.status.success() => Ok(version),
+        _ => bail!("Failed to read the version of {}", bun_path.display()),
+    }
+}
+
+// TODO: Refactor this function when zip crate has async: https://github.com/zip-rs/zip2/pull/73
+async fn extract_bun_bin_of_zip(zip_file_path: &Path, output_dir: &Path) -> Result<PathBuf> {
+    eprintln!("Extracting zip file...");
 
     let zip_file = std::fs::File::open(zip_file_path)?;
 
@@ -132,16 +966,522 @@ async fn extract_bun_bin_of_zip(zip_file_path: &Path, output_dir: &Path) -> Resu
             continue;
         }
 
-        create_dir_all(output_dir.clone()).await?;
+        create_dir_all(output_dir).await?;
 
         let output_path = output_dir.join(bun_bin_name);
         let mut output_file = std::fs::File::create(output_path.clone())?;
         std::io::copy(&mut file_in_archive, &mut output_file)?;
 
-        fs::remove_file(zip_file_path).await?;
+        #[cfg(not(windows))]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            output_file.set_permissions(std::fs::Permissions::from_mode(0o755))?;
+        }
 
         return Ok(output_path);
     }
 
     bail!("Failed to find Bun binary in the zip file")
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_server::{response, serve};
+    use std::io::Write;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// A release zip whose `bun` prints `version`.
+    fn release_zip(dir: &Path, version: &str) -> PathBuf {
+        let zip_path = dir.join("bun-linux-x64.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("bun-linux-x64/bun", options).unwrap();
+        write!(zip, "#!/bin/sh\necho {version}\n").unwrap();
+        zip.finish().unwrap();
+
+        zip_path
+    }
+
+    fn write_checksums(dir: &Path, checksum: &str) {
+        std::fs::write(
+            dir.join(CHECKSUMS_FILE_NAME),
+            format!("{checksum}  bun-linux-x64.zip\n"),
+        )
+        .unwrap();
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_install_from_file_reads_version_from_binary() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let zip_path = release_zip(temp_dir.path(), "1.1.20");
+        write_checksums(temp_dir.path(), &verify::sha256_file(&zip_path).unwrap());
+
+        let (version, bun_path) = install_from_file_in(&zip_path, None, &versions_dir)
+            .await
+            .unwrap();
+
+        assert_eq!(version.to_string(), "1.1.20");
+        assert_eq!(bun_path, versions_dir.join("1.1.20").join("bun"));
+        assert!(zip_path.exists());
+        assert_eq!(installed_dirs(&versions_dir), vec!["1.1.20".to_string()]);
+
+        let error = install_from_file_in(&zip_path, Some("1.1.20"), &versions_dir)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("already installed"));
+        assert_eq!(installed_dirs(&versions_dir), vec!["1.1.20".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_install_from_file_checks_sibling_checksums() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let zip_path = release_zip(temp_dir.path(), "1.1.20");
+        write_checksums(temp_dir.path(), &"f".repeat(64));
+
+        let error = install_from_file_in(&zip_path, Some("1.1.20"), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("Checksum mismatch"));
+        assert!(installed_dirs(&versions_dir).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_install_leaves_nothing_behind() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let zip_path = temp_dir.path().join("bun-linux-x64.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        zip.start_file(
+            "bun-linux-x64/README.md",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.finish().unwrap();
+
+        let error = install_from_file_in(&zip_path, Some("1.1.20"), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("Failed to find Bun binary"));
+        assert!(installed_dirs(&versions_dir).is_empty());
+        assert!(std::fs::read_dir(staging_root_in(&versions_dir))
+            .map_or(true, |mut entries| entries.next().is_none()));
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_install_from_file_checks_requested_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let zip_path = release_zip(temp_dir.path(), "1.1.20");
+
+        let error = install_from_file_in(&zip_path, Some("1.1.21"), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{} holds Bun v1.1.20 instead of v1.1.21, refusing to install it",
+                zip_path.display()
+            )
+        );
+        assert!(installed_dirs(&versions_dir).is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_musl_and_glibc_builds_coexist() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let glibc_zip = release_zip(temp_dir.path(), "1.1.20");
+        let musl_zip = temp_dir.path().join("bun-linux-x64-musl.zip");
+        std::fs::copy(&glibc_zip, &musl_zip).unwrap();
+
+        let (_, glibc_bun) = install_from_file_in(&glibc_zip, Some("1.1.20"), &versions_dir)
+            .await
+            .unwrap();
+        let (_, musl_bun) = install_from_file_in(&musl_zip, Some("1.1.20"), &versions_dir)
+            .await
+            .unwrap();
+
+        let version_dir = versions_dir.join("1.1.20");
+        assert_eq!(glibc_bun, version_dir.join(get_bun_bin_name()));
+        assert_eq!(musl_bun, version_dir.join("musl").join(get_bun_bin_name()));
+        assert!(glibc_bun.is_file() && musl_bun.is_file());
+    }
+
+    pub(crate) fn signing_fixture(file_name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/signing")
+            .join(file_name)
+    }
+
+    /// A `file://` mirror in `dir` with Bun v1.2.3, whose checksums are signed
+    /// by `fixtures/signing/trusted-key.asc`, and whose `linux-x64` zip holds
+    /// `zip_contents`.
+    pub(crate) fn signed_mirror(dir: &Path, zip_contents: &str) -> String {
+        let release_dir = dir.join("mirror").join("bun-v1.2.3");
+        std::fs::create_dir_all(&release_dir).unwrap();
+        for file_name in [CHECKSUMS_FILE_NAME, SIGNATURE_FILE_NAME] {
+            std::fs::copy(signing_fixture(file_name), release_dir.join(file_name)).unwrap();
+        }
+        std::fs::write(release_dir.join("bun-linux-x64.zip"), zip_contents).unwrap();
+
+        format!("file://{}", dir.join("mirror").display())
+    }
+
+    #[tokio::test]
+    async fn test_install_without_trusted_keys_fetches_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let ctx = InstallContext {
+            embedded_keys: &[],
+            ..InstallContext::for_mirror("http://127.0.0.1:9", temp_dir.path(), None)
+        };
+
+        let error = install_release(&ctx, &"1.2.3".parse().unwrap(), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("No trusted release keys to check the signature"));
+        assert!(!staging_root_in(&versions_dir).exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_rejects_checksums_not_signed_by_embedded_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let read_fixture = |file_name| std::fs::read_to_string(signing_fixture(file_name)).unwrap();
+        // The release and its signed checksums, but no zip: a refused install
+        // mustn't download it
+        let (base, server) = serve(&[
+            &response(
+                "200 OK",
+                &[],
+                r#"{"tag_name": "bun-v1.2.3", "assets": [{"name": "bun-linux-x64.zip"}]}"#,
+            ),
+            &response("200 OK", &[], &read_fixture(CHECKSUMS_FILE_NAME)),
+            &response("200 OK", &[], &read_fixture(SIGNATURE_FILE_NAME)),
+        ]);
+        // Built in the way Bun's release key is, but not the signing key
+        let ctx = InstallContext {
+            embedded_keys: &[include_str!("../fixtures/signing/untrusted-key.asc")],
+            ..InstallContext::for_mirror(&base, temp_dir.path(), None)
+        };
+
+        let error = install_release(&ctx, &"1.2.3".parse().unwrap(), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Signature check of SHASUMS256.txt for Bun v1.2.3 failed: \
+             SHASUMS256.txt.asc is not signed by a trusted key. Refusing to install it"
+        );
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("get /bun-v1.2.3/shasums256.txt "));
+        assert!(requests[2].starts_with("get /bun-v1.2.3/shasums256.txt.asc "));
+        assert!(installed_dirs(&versions_dir).is_empty());
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let link = "<https://api.github.com/repositories/1/releases?per_page=100&page=3>; rel=\"next\", \
+                    <https://api.github.com/repositories/1/releases?per_page=100&page=9>; rel=\"last\"";
+        assert_eq!(
+            next_page_url(link).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?per_page=100&page=3")
+        );
+
+        let last_page = "<https://api.github.com/repositories/1/releases?page=8>; rel=\"prev\"";
+        assert_eq!(next_page_url(last_page), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_releases_follows_every_page() {
+        let first_page = r#"[
+            {"tag_name": "bun-v1.1.1", "published_at": "2024-04-01T00:00:00Z", "assets": [{"name": "bun-linux-x64.zip"}]},
+            {"tag_name": "canary"},
+            {"tag_name": "bun-v1.1.2", "draft": true}
+        ]"#;
+        let second_page = r#"[{"tag_name": "bun-v1.0.0"}]"#;
+        let (base, server) = serve(&[
+            &response(
+                "200 OK",
+                &[
+                    "ETag: \"page-1\"",
+                    "Link: <{base}/releases?page=2>; rel=\"next\", <{base}/releases?page=2>; rel=\"last\"",
+                ],
+                first_page,
+            ),
+            &response("200 OK", &[], second_page),
+        ]);
+
+        let fetched = fetch_releases(&reqwest::Client::new(), &base, Some("ghp_test"), None)
+            .await
+            .unwrap();
+        let FetchedReleases::Releases {
+            releases,
+            etag,
+            truncated,
+        } = fetched
+        else {
+            panic!("expected releases");
+        };
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("get /releases?page=2 "));
+        assert!(requests
+            .iter()
+            .all(|request| request.contains("authorization: bearer ghp_test")));
+        let versions: Vec<String> = releases
+            .iter()
+            .map(|release| release.version.to_string())
+            .collect();
+        assert_eq!(versions, ["1.1.1", "1.0.0"]);
+        assert_eq!(releases[0].published_on(), Some("2024-04-01"));
+        assert!(releases[0].has_build_for("linux-x64"));
+        assert!(!releases[0].has_build_for("linux-aarch64"));
+        assert_eq!(etag.as_deref(), Some("\"page-1\""));
+        assert!(!truncated);
+    }
+
+    #[tokio::test]
+    async fn test_token_is_only_sent_to_api() {
+        let (elsewhere, elsewhere_server) =
+            serve(&[&response("200 OK", &[], r#"[{"tag_name": "bun-v1.0.0"}]"#)]);
+        let link = format!("Link: <{elsewhere}/releases?page=2>; rel=\"next\"");
+        let (base, server) = serve(&[&response(
+            "200 OK",
+            &[&link],
+            r#"[{"tag_name": "bun-v1.1.1"}]"#,
+        )]);
+
+        fetch_releases(&reqwest::Client::new(), &base, Some("ghp_test"), None)
+            .await
+            .unwrap();
+
+        assert!(server.join().unwrap()[0].contains("authorization: bearer ghp_test"));
+        assert!(!elsewhere_server.join().unwrap()[0].contains("authorization:"));
+        assert!(same_origin(
+            "https://api.github.com/repositories/1/releases?page=2",
+            "https://api.github.com"
+        ));
+        assert!(!same_origin(
+            "https://api.github.com.evil.test/releases",
+            "https://api.github.com"
+        ));
+        assert!(!same_origin(
+            "http://api.github.com",
+            "https://api.github.com"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_releases_reports_page_limit() {
+        let page = response(
+            "200 OK",
+            &["Link: <{base}/releases?page=next>; rel=\"next\""],
+            r#"[{"tag_name": "bun-v1.0.0"}]"#,
+        );
+        let (base, server) = serve(&vec![page.as_str(); MAX_RELEASE_PAGES]);
+
+        let fetched = fetch_releases(&reqwest::Client::new(), &base, None, None)
+            .await
+            .unwrap();
+        let FetchedReleases::Releases { truncated, .. } = fetched else {
+            panic!("expected releases");
+        };
+
+        assert_eq!(server.join().unwrap().len(), MAX_RELEASE_PAGES);
+        assert!(truncated);
+    }
+
+    #[tokio::test]
+    async fn test_cached_releases_are_revalidated_with_etag() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("remote-versions.json");
+        let (base, server) = serve(&[
+            &response(
+                "200 OK",
+                &["ETag: \"v1\""],
+                r#"[{"tag_name": "bun-v1.1.1"}]"#,
+            ),
+            &response("304 Not Modified", &["ETag: \"v1\""], ""),
+            &response(
+                "200 OK",
+                &["ETag: \"v2\""],
+                r#"[{"tag_name": "bun-v1.1.2"}, {"tag_name": "bun-v1.1.1"}]"#,
+            ),
+        ]);
+        let mut config = Config {
+            download_mirror: base.clone(),
+            github_api_url: base.clone(),
+            download_retries: 0,
+            cache_size_limit: 0,
+            remote_versions_ttl: Duration::from_secs(60 * 60),
+        };
+        let versions = |releases: Vec<Release>| -> Vec<String> {
+            releases
+                .into_iter()
+                .map(|release| release.version.to_string())
+                .collect()
+        };
+
+        let fetched = github_releases(&config, &cache_path, None, false).await;
+        assert_eq!(versions(fetched.unwrap()), ["1.1.1"]);
+        // Fresh, so the API isn't asked
+        let cached = github_releases(&config, &cache_path, None, false).await;
+        assert_eq!(versions(cached.unwrap()), ["1.1.1"]);
+
+        config.remote_versions_ttl = Duration::ZERO;
+        let revalidated = github_releases(&config, &cache_path, None, false).await;
+        assert_eq!(versions(revalidated.unwrap()), ["1.1.1"]);
+
+        let refreshed = github_releases(&config, &cache_path, None, true).await;
+        assert_eq!(versions(refreshed.unwrap()), ["1.1.2", "1.1.1"]);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(!requests[2].contains("if-none-match"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_reported() {
+        let (base, server) = serve(&[&response(
+            "403 Forbidden",
+            &[
+                "X-RateLimit-Limit: 60",
+                "X-RateLimit-Remaining: 0",
+                "X-RateLimit-Reset: 1711974600",
+            ],
+            r#"{"message": "API rate limit exceeded for 127.0.0.1."}"#,
+        )]);
+
+        let error = fetch_releases(&reqwest::Client::new(), &base, None, None)
+            .await
+            .err()
+            .unwrap();
+
+        assert!(!server.join().unwrap()[0].contains("authorization:"));
+        assert_eq!(
+            error.to_string(),
+            "GitHub API rate limit of 60 requests per hour exceeded, it resets at \
+             2024-04-01 12:30:00 UTC. Set GITHUB_TOKEN or GH_TOKEN to a GitHub token to raise the limit"
+        );
+    }
+
+    #[test]
+    fn test_missing_build_lists_available_platforms() {
+        let release = Release {
+            version: "1.0.0".parse().unwrap(),
+            published_at: None,
+            assets: [
+                "SHASUMS256.txt",
+                "bun-linux-x64.zip",
+                "bun-darwin-aarch64.zip",
+                "bun-linux-x64-profile.zip",
+            ]
+            .iter()
+            .map(|asset| asset.to_string())
+            .collect(),
+        };
+
+        assert!(missing_build_error(&release, "linux-x64").is_none());
+        assert_eq!(
+            missing_build_error(&release, "linux-aarch64")
+                .unwrap()
+                .to_string(),
+            "Bun v1.0.0 exists but has no build for this platform (linux-aarch64). \
+             Available platforms: darwin-aarch64, linux-x64"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_install_without_build_downloads_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let versions_dir = temp_dir.path().join("bun-versions");
+        let (base, server) = serve(&[&response(
+            "200 OK",
+            &[],
+            r#"{"tag_name": "bun-v1.2.3", "assets": [{"name": "bun-darwin-aarch64.zip"}]}"#,
+        )]);
+        let ctx = InstallContext::for_mirror(
+            &base,
+            temp_dir.path(),
+            Some(&signing_fixture("trusted-key.asc")),
+        );
+
+        let error = install_release(&ctx, &"1.2.3".parse().unwrap(), &versions_dir)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Bun v1.2.3 exists but has no build for this platform (linux-x64). \
+             Available platforms: darwin-aarch64"
+        );
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("get /repos/oven-sh/bun/releases/tags/bun-v1.2.3 "));
+        assert!(!staging_root_in(&versions_dir).exists());
+    }
+
+    #[tokio::test]
+    async fn test_release_check_uses_cached_list_and_reports_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let (base, server) = serve(&[&response("500 Internal Server Error", &[], "")]);
+        let ctx = InstallContext::for_mirror(&base, temp_dir.path(), None);
+        CachedReleases {
+            format: CACHE_FORMAT,
+            api_url: base.clone(),
+            fetched_at: 0,
+            etag: None,
+            releases: vec![Release {
+                version: "1.0.0".parse().unwrap(),
+                published_at: None,
+                assets: vec!["bun-darwin-aarch64.zip".to_string()],
+            }],
+        }
+        .write(&ctx.remote_versions_cache_path)
+        .unwrap();
+
+        // Even a stale list knows the assets of an old release
+        let error = check_release_has_build(&ctx, &"1.0.0".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("has no build for this platform"));
+
+        let error = check_release_has_build(&ctx, &"1.2.3".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to fetch the Bun releases: 500 Internal Server Error"
+        );
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    fn installed_dirs(versions_dir: &Path) -> Vec<String> {
+        if !versions_dir.exists() {
+            return Vec::new();
+        }
+        std::fs::read_dir(versions_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| !name.starts_with('.'))
+            .collect()
+    }
+}
@@ -0,0 +1,269 @@
+use crate::verify::sha256_file;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Downloaded release archives, stored under their SHA-256 so a reinstall
+/// can skip the download whenever the release checksum is already known.
+///
+/// Every `<sha256>.zip` has a `<sha256>.json` next to it saying which
+/// release it came from. The modification time of the archive is its last
+/// use, so the least recently used archives are evicted first.
+pub struct ArchiveCache {
+    dir: PathBuf,
+    size_limit: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryInfo {
+    version: String,
+    asset: String,
+}
+
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub digest: String,
+    pub version: String,
+    pub asset: String,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+impl ArchiveCache {
+    pub fn new(dir: &Path, size_limit: u64) -> Self {
+        ArchiveCache {
+            dir: dir.to_path_buf(),
+            size_limit,
+        }
+    }
+
+    fn archive_path(&self, digest: &str) -> PathBuf {
+        self.dir.join(format!("{}.zip", digest))
+    }
+
+    fn info_path(&self, digest: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", digest))
+    }
+
+    /// The cached archive with this SHA-256, if there is one and its
+    /// contents still match.
+    pub fn get(&self, digest: &str) -> Option<PathBuf> {
+        let archive_path = self.archive_path(digest);
+        if !archive_path.is_file() {
+            return None;
+        }
+
+        if sha256_file(&archive_path).ok()?.as_str() != digest {
+            let _ = self.remove(digest);
+            return None;
+        }
+
+        if let Ok(file) = std::fs::File::options().write(true).open(&archive_path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(archive_path)
+    }
+
+    /// Copies a verified archive into the cache, then evicts the least
+    /// recently used archives until the cache fits its size limit again.
+    pub fn put(&self, archive: &Path, digest: &str, version: &str, asset: &str) -> Result<()> {
+        if self.size_limit == 0 {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Copy next to the final path first, so a cut-off copy is never used
+        let partial_path = self.dir.join(format!("{}.zip.tmp", digest));
+        std::fs::copy(archive, &partial_path)?;
+        std::fs::rename(&partial_path, self.archive_path(digest))?;
+
+        let info = EntryInfo {
+            version: version.to_string(),
+            asset: asset.to_string(),
+        };
+        std::fs::write(self.info_path(digest), serde_json::to_string(&info)?)?;
+
+        self.evict()?;
+
+        Ok(())
+    }
+
+    /// Cached archives, least recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+
+        if !self.dir.is_dir() {
+            return Ok(entries);
+        }
+
+        for dir_entry in std::fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("zip") {
+                continue;
+            }
+            let Some(digest) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let metadata = std::fs::metadata(&path)?;
+            let info: Option<EntryInfo> = std::fs::read_to_string(self.info_path(digest))
+                .ok()
+                .and_then(|info| serde_json::from_str(&info).ok());
+
+            entries.push(CacheEntry {
+                digest: digest.to_string(),
+                version: info
+                    .as_ref()
+                    .map_or_else(|| "unknown".to_string(), |info| info.version.clone()),
+                asset: info.map_or_else(|| "unknown".to_string(), |info| info.asset),
+                size: metadata.len(),
+                last_used: metadata.modified()?,
+            });
+        }
+
+        entries.sort_by_key(|entry| entry.last_used);
+
+        Ok(entries)
+    }
+
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    pub fn size_limit(&self) -> u64 {
+        self.size_limit
+    }
+
+    fn remove(&self, digest: &str) -> Result<()> {
+        std::fs::remove_file(self.archive_path(digest))?;
+        if self.info_path(digest).exists() {
+            std::fs::remove_file(self.info_path(digest))?;
+        }
+        Ok(())
+    }
+
+    fn evict(&self) -> Result<()> {
+        let entries = self.entries()?;
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+        for entry in entries {
+            if size <= self.size_limit {
+                break;
+            }
+            self.remove(&entry.digest)?;
+            size -= entry.size;
+        }
+
+        Ok(())
+    }
+
+    /// Removes every cached archive and returns the number of bytes freed.
+    pub fn clean(&self) -> Result<u64> {
+        let mut freed = 0;
+
+        for entry in self.entries()? {
+            self.remove(&entry.digest)?;
+            freed += entry.size;
+        }
+
+        Ok(freed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    // sha256("mock bun zip")
+    const MOCK_ZIP_SHA256: &str =
+        "0b3e44c0d506ab20e54574806b9bd2a4833dfc4621aa6e9f1fc56c31443d9d65";
+
+    fn archive_with(dir: &Path, contents: &str) -> (PathBuf, String) {
+        let path = dir.join("archive.zip");
+        std::fs::write(&path, contents).unwrap();
+        let digest = sha256_file(&path).unwrap();
+        (path, digest)
+    }
+
+    fn set_last_used(cache: &ArchiveCache, digest: &str, seconds_ago: u64) {
+        let file = std::fs::File::options()
+            .write(true)
+            .open(cache.archive_path(digest))
+            .unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(seconds_ago))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_cached_archive_is_found_by_checksum() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ArchiveCache::new(&temp_dir.path().join("cache"), 1024);
+        let (archive, digest) = archive_with(temp_dir.path(), "mock bun zip");
+        assert_eq!(digest, MOCK_ZIP_SHA256);
+
+        assert!(cache.get(&digest).is_none());
+        cache
+            .put(&archive, &digest, "1.1.0", "bun-linux-x64.zip")
+            .unwrap();
+
+        let cached = cache.get(&digest).unwrap();
+        assert_eq!(std::fs::read_to_string(cached).unwrap(), "mock bun zip");
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, "1.1.0");
+        assert_eq!(entries[0].asset, "bun-linux-x64.zip");
+        assert_eq!(cache.size().unwrap(), "mock bun zip".len() as u64);
+    }
+
+    #[test]
+    fn test_corrupted_archive_is_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = ArchiveCache::new(&temp_dir.path().join("cache"), 1024);
+        let (archive, digest) = archive_with(temp_dir.path(), "mock bun zip");
+        cache
+            .put(&archive, &digest, "1.1.0", "bun-linux-x64.zip")
+            .unwrap();
+
+        std::fs::write(cache.archive_path(&digest), "corrupted").unwrap();
+
+        assert!(cache.get(&digest).is_none());
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_archives_are_evicted() {
+        let temp_dir = TempDir::new().unwrap();
+        // Room for two of the 10 byte archives below
+        let cache = ArchiveCache::new(&temp_dir.path().join("cache"), 25);
+
+        let mut digests = Vec::new();
+        for (i, contents) in ["archive 1.", "archive 2.", "archive 3."]
+            .iter()
+            .enumerate()
+        {
+            let (archive, digest) = archive_with(temp_dir.path(), contents);
+            cache
+                .put(&archive, &digest, &format!("1.1.{i}"), "bun-linux-x64.zip")
+                .unwrap();
+            set_last_used(&cache, &digest, 100 - i as u64 * 10);
+            digests.push(digest);
+        }
+
+        let versions: Vec<String> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.version)
+            .collect();
+        assert_eq!(versions, ["1.1.1", "1.1.2"]);
+
+        assert_eq!(cache.clean().unwrap(), 20);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
@@ -1,11 +1,20 @@
 use crate::bun;
-use crate::bun::BUN_BIN_NAME;
+use crate::bun::{InstallContext, BUN_BIN_NAME};
+use crate::cache::ArchiveCache;
+use crate::config::Config;
+use crate::os;
+use crate::shell::Shell;
+use crate::shim;
 use crate::utils;
-use anyhow::Result;
+use crate::version::{BunVersion, VersionRequest};
+use crate::version_file::{find_version_file, VersionFile, VersionFileKind};
+use anyhow::{anyhow, bail, Result};
+use indicatif::HumanBytes;
 use lazy_static::lazy_static;
 use owo_colors::{self, DynColors, OwoColorize};
 use resolve_path::PathResolveExt;
 use std::borrow::Cow;
+use std::io::{IsTerminal, Write};
 #[cfg(not(windows))]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
@@ -16,75 +25,313 @@ use utils::check_folder_exists;
 lazy_static! {
     pub static ref FOLDER_VERSION_BASE: Cow<'static, Path> = "~/.bum/bun-versions".resolve();
     pub static ref BUN_BIN_PATH: Cow<'static, Path> = "~/.bun/bin/bun".resolve();
+    pub static ref DEFAULT_VERSION_PATH: Cow<'static, Path> = "~/.bum/default".resolve();
+    pub static ref SESSIONS_DIR: Cow<'static, Path> = "~/.bum/sessions".resolve();
+    pub static ref CACHE_DIR: Cow<'static, Path> = "~/.bum/cache".resolve();
+    pub static ref CONFIG_PATH: Cow<'static, Path> = "~/.bum/config.json".resolve();
+    pub static ref TRUSTED_KEYS_DIR: Cow<'static, Path> = "~/.bum/trusted-keys".resolve();
+    pub static ref REMOTE_VERSIONS_CACHE_PATH: Cow<'static, Path> =
+        "~/.bum/remote-versions.json".resolve();
 }
 
-pub fn normalize_version(version: &str) -> String {
-    version.replace('v', "")
+/// Set by `bum env` to the session directory of the current shell.
+const SESSION_ENV_VAR: &str = "BUM_SESSION";
+
+fn get_project_version() -> Result<VersionFile, &'static str> {
+    let current_dir = std::env::current_dir().map_err(|_| "Failed to read current directory")?;
+    find_version_file(&current_dir).ok_or("no version file found")
 }
 
-async fn get_bumrc_version() -> Result<String, &'static str> {
-    let bumrc_path = Path::new(".bumrc");
-    if bumrc_path.exists() {
-        let bumrc_version = fs::read_to_string(".bumrc")
-            .await
-            .expect("Failed to read .bumrc, is it a valid file?");
-        Ok(bumrc_version.trim().to_string())
-    } else {
-        Err("No .bumrc file found")
+/// The directory with the `bun` of `version` for the build of this system.
+/// The regular build lives in the version directory itself, other builds
+/// (e.g. `musl`) in a directory of their own inside it.
+pub fn version_dir_in(versions_dir: &Path, version: &BunVersion) -> PathBuf {
+    build_dir_in(versions_dir, version, os::get_build().as_deref())
+}
+
+pub fn build_dir_in(versions_dir: &Path, version: &BunVersion, build: Option<&str>) -> PathBuf {
+    let version_dir = versions_dir.join(version.to_string());
+    match build {
+        Some(build) => version_dir.join(build),
+        None => version_dir,
+    }
+}
+
+/// Lists the versions installed in `versions_dir` for the build of this
+/// system, ignoring anything that isn't a version directory.
+/// Synchronous so that the `bun` shim can use it without a runtime.
+pub fn installed_versions_in(versions_dir: &Path) -> Result<Vec<BunVersion>> {
+    let mut versions = Vec::new();
+
+    if !versions_dir.is_dir() {
+        return Ok(versions);
+    }
+
+    for entry in std::fs::read_dir(versions_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Ok(version) = entry.file_name().to_string_lossy().parse::<BunVersion>() {
+            if version_dir_in(versions_dir, &version)
+                .join(BUN_BIN_NAME)
+                .is_file()
+            {
+                versions.push(version);
+            }
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Turns a version, partial version, range or `latest` into an exact version.
+/// Installed versions are preferred over remote ones, except for `latest`,
+/// which is always the newest release in the remote list. That list is
+/// cached for `remote_versions_ttl`, see `bun::get_releases`.
+pub async fn resolve_version(input: &str) -> Result<BunVersion> {
+    resolve_version_in(&InstallContext::load()?, input, &FOLDER_VERSION_BASE).await
+}
+
+async fn resolve_version_in(
+    ctx: &InstallContext,
+    input: &str,
+    versions_dir: &Path,
+) -> Result<BunVersion> {
+    let request = VersionRequest::parse(input)?;
+
+    if let VersionRequest::Exact(version) = request {
+        return Ok(version);
+    }
+
+    if request != VersionRequest::Latest {
+        let installed = installed_versions_in(versions_dir)?;
+        if let Some(version) = request.newest_match(&installed) {
+            return Ok(version.clone());
+        }
     }
+
+    let remote = bun::get_remote_versions(ctx, false).await?;
+    request
+        .newest_match(&remote)
+        .cloned()
+        .ok_or_else(|| anyhow!("No Bun version matches \"{}\"", input.trim()))
 }
 
 pub async fn use_bun(version: &str) -> Result<()> {
-    let version = normalize_version(version);
+    use_bun_in(&InstallContext::load()?, version, &FOLDER_VERSION_BASE).await
+}
+
+async fn use_bun_in(ctx: &InstallContext, version: &str, versions_dir: &Path) -> Result<()> {
+    let requested = version.trim();
+    let version = resolve_version_in(ctx, requested, versions_dir).await?;
+
+    if requested.parse::<BunVersion>().is_err() {
+        println!("Resolved {} to v{}", requested, version);
+    }
 
     let active_color: DynColors = "#eea990".parse().unwrap();
     let active_style = owo_colors::Style::new().color(active_color).bold();
 
-    let path_with_version = FOLDER_VERSION_BASE.join(&version);
+    let bun_used_path = install_version_in(ctx, &version, versions_dir).await?;
+    activate_bun(bun_used_path)
+        .await
+        .map_err(|e| anyhow!("Failed to activate Bun v{}: {}", version, e))?;
 
-    if check_folder_exists(&path_with_version).await {
-        let bun_used_path = path_with_version.join(BUN_BIN_NAME);
+    println!(
+        "Bun {} is activated.",
+        format!("v{}", version).style(active_style)
+    );
 
-        match activate_bun(bun_used_path).await {
-            Ok(()) => {
-                println!(
-                    "Bun {} is activated.",
-                    format!("v{}", version).style(active_style)
-                );
-            }
-            Err(e) => {
-                eprintln!("Failed to activate Bun v{}", version);
-                eprintln!("Reason: {e:?}");
-            }
-        }
+    Ok(())
+}
 
-        return Ok(());
+/// Installs `version` unless it's already installed, and returns the path of
+/// its `bun` binary.
+pub async fn install_version(version: &BunVersion) -> Result<PathBuf> {
+    install_version_in(&InstallContext::load()?, version, &FOLDER_VERSION_BASE).await
+}
+
+async fn install_version_in(
+    ctx: &InstallContext,
+    version: &BunVersion,
+    versions_dir: &Path,
+) -> Result<PathBuf> {
+    let bun_path = version_dir_in(versions_dir, version).join(BUN_BIN_NAME);
+
+    if check_folder_exists(&bun_path).await {
+        return Ok(bun_path);
     }
 
-    println!("Bum - installing bun for version {}...", version);
+    // On stderr, so it doesn't mix with the output of `bum exec`
+    eprintln!("Bum - installing bun for version {}...", version);
 
-    if fs::metadata(FOLDER_VERSION_BASE.to_owned()).await.is_err() {
-        fs::create_dir_all(FOLDER_VERSION_BASE.to_owned()).await?;
+    bun::install_release(ctx, version, versions_dir).await
+}
+
+/// Installs a Bun release zip from disk without activating it.
+pub async fn install_from_file(zip_path: &Path, version: Option<&str>) -> Result<()> {
+    let (version, _) = bun::install_from_file(zip_path, version).await?;
+    println!(
+        "Bun v{} is installed, please use bum use {} to activate it",
+        version, version
+    );
+
+    Ok(())
+}
+
+/// Runs `command` with `version` first on its `PATH`, installing the version
+/// if needed, but without activating it. On Unix bum replaces itself with the
+/// command, so its exit code and signals are exactly the command's own.
+pub async fn exec(version: &str, command: &[String]) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("No command given, please use bum exec <version> -- <command>");
+    };
+
+    let version = resolve_version(version).await?;
+    let bun_used_path = install_version(&version).await?;
+    let version_dir = bun_used_path
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid install path for Bun v{}", version))?;
+
+    exec_in(version_dir, program, args)
+}
+
+/// `program` with `bin_dir` first on its `PATH`.
+fn command_with_bin_dir(
+    bin_dir: &Path,
+    program: &str,
+    args: &[String],
+) -> Result<std::process::Command> {
+    let mut paths = vec![bin_dir.to_path_buf()];
+    if let Some(path) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&path));
     }
 
-    let zip_file_path = FOLDER_VERSION_BASE.join(format!("{}.zip", version));
+    let mut command = std::process::Command::new(program);
+    command.args(args).env("PATH", std::env::join_paths(paths)?);
+    Ok(command)
+}
 
-    match bun::download_version_to(&version, &zip_file_path).await {
-        Ok(bun_path) => match activate_bun(bun_path).await {
-            Ok(()) => println!(
-                "Bun {} is activated.",
-                format!("v{}", version).style(active_style)
-            ),
-            e => eprintln!("Failed {e:?}"),
-        },
-        Err(err) => eprintln!("{err}"),
+/// Runs `program` with `bin_dir` first on its `PATH` in place of bum. Only
+/// returns when the command can't be started.
+fn exec_in(bin_dir: &Path, program: &str, args: &[String]) -> Result<()> {
+    let mut command = command_with_bin_dir(bin_dir, program, args)?;
+
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let error = command.exec();
+        bail!("Failed to run {}: {}", program, error);
     }
 
+    #[cfg(windows)]
+    {
+        let status = command
+            .status()
+            .map_err(|e| anyhow!("Failed to run {}: {}", program, e))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// Pins a version for the project in the current directory, installing it if
+/// needed. Without a version, the active one is pinned.
+///
+/// The nearest version file bum reads is updated in place. `engines.bun` is
+/// left alone since it lists compatible versions rather than a pin, so a
+/// `.bumrc` is written next to that `package.json` instead. Without any
+/// version file, a `.bumrc` is created in the current directory.
+pub async fn pin(version: Option<&str>) -> Result<()> {
+    let requested = match version {
+        Some(version) => version.trim().to_string(),
+        None => {
+            let active_version = bun::get_active_version().await;
+            if active_version.is_empty() {
+                bail!("No active Bun version to pin, please use bum pin <version>");
+            }
+            active_version
+        }
+    };
+
+    let version = resolve_version(&requested).await?;
+    install_version(&version).await?;
+
+    let current_dir = std::env::current_dir()?;
+    let pinned_path = match find_version_file(&current_dir) {
+        Some(version_file) if version_file.kind != VersionFileKind::Engines => {
+            version_file.write_version(&version.to_string())?;
+            version_file.path
+        }
+        Some(version_file) => {
+            let dir = version_file.path.parent().unwrap_or(&current_dir);
+            write_bumrc_to(&version, dir).await?
+        }
+        None => write_bumrc_to(&version, &current_dir).await?,
+    };
+
+    println!("Pinned Bun v{} in {}", version, pinned_path.display());
+
     Ok(())
 }
 
+async fn write_bumrc_to(version: &BunVersion, dir: &Path) -> Result<PathBuf> {
+    let bumrc_path = dir.join(VersionFileKind::Bumrc.file_name());
+    fs::write(&bumrc_path, format!("{version}\n")).await?;
+
+    Ok(bumrc_path)
+}
+
+/// Makes `bun_used_path` the active `bun`. Inside a shell set up by `bum env`
+/// only that shell's session is switched, otherwise the global `bun` is.
+/// With the `bun` shim installed, the global switch sets the default version
+/// the shim falls back to instead of replacing the shim.
 pub async fn activate_bun(bun_used_path: PathBuf) -> Result<()> {
-    activate_bun_to(bun_used_path, BUN_BIN_PATH.to_path_buf()).await
+    match session_bin_dir() {
+        Some(session_bin_dir) => {
+            link_bun_to(bun_used_path, session_bin_dir.join(BUN_BIN_NAME)).await
+        }
+        None if shim::is_installed() => {
+            // The version directory may hold the build directory of `bun`
+            let version = bun_used_path
+                .ancestors()
+                .filter_map(|dir| dir.file_name())
+                .find_map(|name| name.to_string_lossy().parse::<BunVersion>().ok())
+                .ok_or_else(|| anyhow!("Invalid Bun path {}", bun_used_path.display()))?
+                .to_string();
+            write_default_to(&version, &DEFAULT_VERSION_PATH).await?;
+            println!(
+                "The bun shim is installed, v{} is now the default outside of pinned projects.",
+                version
+            );
+            Ok(())
+        }
+        None => activate_bun_to(bun_used_path, BUN_BIN_PATH.to_path_buf()).await,
+    }
+}
+
+fn session_bin_dir() -> Option<PathBuf> {
+    std::env::var_os(SESSION_ENV_VAR).map(|session_dir| PathBuf::from(session_dir).join("bin"))
+}
+
+/// Points a session's `bun` at an installed version. A symlink is enough
+/// here, and unlike a copy it doesn't get in the way of other sessions.
+async fn link_bun_to(bun_used_path: PathBuf, target_path: PathBuf) -> Result<()> {
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    if fs::symlink_metadata(&target_path).await.is_ok() {
+        fs::remove_file(&target_path).await?;
+    }
+
+    #[cfg(not(windows))]
+    fs::symlink(bun_used_path, &target_path).await?;
+
+    #[cfg(windows)]
+    fs::copy(bun_used_path, &target_path).await?;
+
+    Ok(())
 }
 
 fn is_bun_bin_in_path() -> bool {
@@ -110,6 +357,11 @@ async fn activate_bun_to(bun_used_path: PathBuf, target_path: PathBuf) -> Result
         }
     }
 
+    // Never write through a link, e.g. a session link or the bun shim
+    if fs::symlink_metadata(&target_path).await.is_ok() {
+        fs::remove_file(&target_path).await?;
+    }
+
     fs::copy(bun_used_path, &target_path).await?;
 
     let metadata = fs::metadata(&target_path).await?;
@@ -136,11 +388,11 @@ async fn activate_bun_to(bun_used_path: PathBuf, target_path: PathBuf) -> Result
     Ok(())
 }
 
-pub async fn remove(version: &str) {
-    let version = normalize_version(version);
+pub async fn remove(version: &str) -> Result<()> {
+    let version: BunVersion = version.parse()?;
 
-    println!("{:?}", FOLDER_VERSION_BASE.join(&version));
-    let result = remove_dir_all(FOLDER_VERSION_BASE.join(&version)).await;
+    println!("{:?}", FOLDER_VERSION_BASE.join(version.to_string()));
+    let result = remove_dir_all(FOLDER_VERSION_BASE.join(version.to_string())).await;
     match result {
         Ok(()) => {
             println!("v{} has been removed.", version);
@@ -152,34 +404,24 @@ pub async fn remove(version: &str) {
             )
         }
     }
+
+    Ok(())
 }
 
 pub async fn list() -> Result<()> {
-    let mut versions_list: Vec<String> = Vec::new();
-
     if fs::metadata(FOLDER_VERSION_BASE.to_owned()).await.is_err() {
         return Ok(());
     }
 
-    match fs::read_dir(FOLDER_VERSION_BASE.to_owned()).await {
-        Ok(mut entries) => {
-            while let Some(entry) = entries.next_entry().await? {
-                let path_buf: PathBuf = entry.path();
-                let path_str = path_buf.to_string_lossy().to_string();
-
-                // Normalize path separators
-                let path_str = path_str.replace('\\', "/");
-
-                let version = path_str.split('/').last().unwrap_or_default();
-                versions_list.push(version.to_string());
-            }
+    match installed_versions_in(&FOLDER_VERSION_BASE) {
+        Ok(mut versions_list) => {
             versions_list.sort();
             versions_list.reverse();
-            let active_version = bun::get_active_version().await;
+            let active_version = bun::get_active_version().await.parse::<BunVersion>().ok();
             let active_color: DynColors = "#eea990".parse().unwrap();
             let active_style = owo_colors::Style::new().color(active_color).bold();
             for version in versions_list {
-                if version == active_version {
+                if Some(&version) == active_version.as_ref() {
                     let active_version = format!("{} (active)", version.style(active_style));
                     println!("{} {active_version}", "•".style(active_style));
                 } else {
@@ -195,42 +437,434 @@ pub async fn list() -> Result<()> {
     Ok(())
 }
 
+fn archive_cache() -> Result<ArchiveCache> {
+    let config = Config::load()?;
+    Ok(ArchiveCache::new(&CACHE_DIR, config.cache_size_limit))
+}
+
+pub async fn cache_list() -> Result<()> {
+    let entries = archive_cache()?.entries()?;
+
+    if entries.is_empty() {
+        println!("The cache is empty");
+        return Ok(());
+    }
+
+    // Most recently used first
+    for entry in entries.iter().rev() {
+        println!(
+            "• v{} {} ({}, sha256 {})",
+            entry.version,
+            entry.asset,
+            HumanBytes(entry.size),
+            &entry.digest[..entry.digest.len().min(12)]
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn cache_size() -> Result<()> {
+    let cache = archive_cache()?;
+
+    println!(
+        "{} of {} used in {}",
+        HumanBytes(cache.size()?),
+        HumanBytes(cache.size_limit()),
+        CACHE_DIR.display()
+    );
+
+    Ok(())
+}
+
+pub async fn cache_clean() -> Result<()> {
+    let freed = archive_cache()?.clean()?;
+    println!("Removed the cached archives, freeing {}", HumanBytes(freed));
+
+    Ok(())
+}
+
 pub async fn use_bumrc() -> Result<()> {
-    let bumrc_version = get_bumrc_version().await;
-    match bumrc_version {
-        Ok(version) => {
-            println!("Using version {} from .bumrc", version);
-            use_bun(&version).await?;
-        }
-        Err(e) => {
+    match get_project_version() {
+        Ok(version_file) => {
             println!(
-                "No version specified or {}, please use bum use <version> or use -h to print help",
-                e
+                "Using version {} from {}",
+                version_file.version, version_file
             );
+            use_bun(&version_file.version).await?;
         }
+        Err(e) => match read_default_from(&DEFAULT_VERSION_PATH)? {
+            Some(version) => {
+                println!("Using default version {}", version);
+                use_bun(&version).await?;
+            }
+            None => {
+                println!(
+                    "No version specified or {}, please use bum use <version> or use -h to print help",
+                    e
+                );
+            }
+        },
     }
 
     Ok(())
 }
 
-pub async fn list_remote() {
-    let tags = bun::get_github_tags().await;
+/// The Bun version pinned for a directory, resolved against what's installed.
+#[derive(Debug, PartialEq)]
+pub enum PinnedVersion {
+    /// The newest installed version matching the pin
+    Installed { version: BunVersion, source: String },
+    /// Nothing installed matches the pinned `version`
+    Missing { version: String, source: String },
+}
+
+/// Resolves the version pinned for `dir` (or the default version) against
+/// the installed versions only. It's synchronous, so it can't reach the
+/// network: the shell hook runs it on every `cd`, and the `bun` shim on
+/// every `bun` invocation, so it must stay fast and offline.
+pub fn pinned_version_in(
+    dir: &Path,
+    versions_dir: &Path,
+    default_path: &Path,
+) -> Result<Option<PinnedVersion>> {
+    let (version, source) = match find_version_file(dir) {
+        Some(version_file) => (version_file.version.clone(), version_file.to_string()),
+        None => match read_default_from(default_path)? {
+            Some(version) => (version, "the default version".to_string()),
+            None => return Ok(None),
+        },
+    };
+
+    let request = VersionRequest::parse(&version)?;
+    let installed = installed_versions_in(versions_dir)?;
+
+    Ok(Some(match request.newest_match(&installed) {
+        Some(installed_version) => PinnedVersion::Installed {
+            version: installed_version.clone(),
+            source,
+        },
+        None => PinnedVersion::Missing { version, source },
+    }))
+}
 
-    match tags {
-        Ok(tags) => {
-            for tag in tags {
-                println!("  {}", tag);
+/// Run by the shell hook from `bum env --use-on-cd` on every directory change.
+/// It switches to the pinned (or default) version when that isn't active yet,
+/// resolving only against installed versions so it never needs the network,
+/// and asks before installing a missing version.
+pub async fn use_on_cd() -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let pinned = pinned_version_in(&current_dir, &FOLDER_VERSION_BASE, &DEFAULT_VERSION_PATH)?;
+
+    let (installed_version, source) = match pinned {
+        None => return Ok(()),
+        Some(PinnedVersion::Installed { version, source }) => (version, source),
+        Some(PinnedVersion::Missing { version, source }) => {
+            if !std::io::stdin().is_terminal() {
+                eprintln!(
+                    "Bun {} from {} is not installed, run bum use to install it",
+                    version, source
+                );
+                return Ok(());
+            }
+
+            eprint!(
+                "Bun {} from {} is not installed. Install it now? [y/N] ",
+                version, source
+            );
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+            if answer.trim().eq_ignore_ascii_case("y") || answer.trim().eq_ignore_ascii_case("yes")
+            {
+                use_bun(&version).await?;
             }
+            return Ok(());
         }
-        Err(e) => {
-            println!("Failed to get remote version list: {}", e);
+    };
+
+    let active_version = bun::get_active_version().await;
+    if active_version.parse::<BunVersion>().ok().as_ref() == Some(&installed_version) {
+        return Ok(());
+    }
+
+    println!("Using Bun v{} from {}", installed_version, source);
+    activate_bun(version_dir_in(&FOLDER_VERSION_BASE, &installed_version).join(BUN_BIN_NAME)).await
+}
+
+/// Creates a session directory for a new shell and prints the code that
+/// puts its `bin` first on `PATH`, so `bum use` in that shell only changes
+/// its own version. With `use_on_cd`, the hook that switches versions when
+/// changing directories is printed as well.
+pub async fn env(use_on_cd: bool, shell: Option<Shell>) -> Result<()> {
+    let Some(shell) = shell.or_else(Shell::detect) else {
+        bail!("Failed to detect your shell, please pass --shell bash|zsh|fish");
+    };
+
+    let session_dir = create_session_in(&SESSIONS_DIR, shell_pid()).await?;
+
+    // Until `bum use` runs in the session, the global bun stays visible
+    if let Some(bun_bin_dir) = BUN_BIN_PATH.parent() {
+        println!("{}", shell.prepend_path(bun_bin_dir));
+    }
+    println!("{}", shell.prepend_path(&session_dir.join("bin")));
+    println!("{}", shell.set_env(SESSION_ENV_VAR, &session_dir));
+    println!("{}", shell.set_env("BUN_INSTALL", &session_dir));
+
+    if use_on_cd {
+        println!("{}", shell.use_on_cd_hook());
+    }
+
+    Ok(())
+}
+
+/// The pid of the shell running `bum env`. `eval "$(bum env)"` and
+/// `bum env | source` run bum as a direct child of the shell.
+fn shell_pid() -> u32 {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::parent_id()
+    }
+    #[cfg(not(unix))]
+    {
+        std::process::id()
+    }
+}
+
+fn process_is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        // Signal 0 only checks that the process exists. EPERM means it does,
+        // but belongs to another user.
+        let exists = unsafe { libc::kill(pid, 0) } == 0;
+        exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        // Can't tell, so keep the session
+        let _ = pid;
+        true
+    }
+}
+
+/// Session directories are named `<shell pid>_<millis>_<n>`.
+fn session_shell_pid(session_name: &str) -> Option<u32> {
+    session_name.split('_').next()?.parse().ok()
+}
+
+/// Removes the session directories of shells that have exited.
+async fn prune_sessions_in(sessions_dir: &Path, is_running: impl Fn(u32) -> bool) -> Result<()> {
+    let mut entries = match fs::read_dir(sessions_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str().and_then(session_shell_pid) else {
+            continue;
+        };
+        if !is_running(pid) {
+            // Another shell starting at the same time may have removed it
+            let _ = remove_dir_all(entry.path()).await;
         }
     }
+
+    Ok(())
+}
+
+async fn create_session_in(sessions_dir: &Path, shell_pid: u32) -> Result<PathBuf> {
+    prune_sessions_in(sessions_dir, process_is_running).await?;
+    fs::create_dir_all(sessions_dir).await?;
+
+    let started_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis();
+
+    let mut attempt = 0;
+    loop {
+        let session_dir = sessions_dir.join(format!("{}_{}_{}", shell_pid, started_at, attempt));
+        match fs::create_dir(&session_dir).await {
+            Ok(()) => {
+                fs::create_dir(session_dir.join("bin")).await?;
+                return Ok(session_dir);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The default version written at `path`, if any. Plain `std::fs`, as the
+/// shim reads it on every `bun` run.
+pub fn read_default_from(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let version = std::fs::read_to_string(path)?;
+    let version = version.trim();
+
+    if version.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(version.to_string()))
+    }
+}
+
+async fn write_default_to(version: &str, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    fs::write(path, format!("{version}\n")).await?;
+
+    Ok(())
+}
+
+/// Saves the version `bum use` falls back to when there's no `.bumrc`.
+/// Ranges are kept as written, so `bum default 1.1` follows new 1.1.x releases.
+pub async fn set_default(version: &str) -> Result<()> {
+    let version = match VersionRequest::parse(version)? {
+        VersionRequest::Exact(version) => version.to_string(),
+        _ => version.trim().to_string(),
+    };
+
+    write_default_to(&version, &DEFAULT_VERSION_PATH).await?;
+    println!("Default version is now {}", version);
+
+    Ok(())
+}
+
+pub async fn print_default() -> Result<()> {
+    match read_default_from(&DEFAULT_VERSION_PATH)? {
+        Some(version) => println!("{}", version),
+        None => println!("No default version set, use bum default <version> to set one"),
+    }
+
+    Ok(())
+}
+
+/// Which remote versions `bum list-remote` shows.
+#[derive(Debug, Default)]
+pub struct RemoteFilter {
+    /// Only versions starting with this, e.g. `1.1` for every 1.1.x
+    pub prefix: Option<String>,
+    /// Only the newest this many versions left after the other filters
+    pub latest: Option<usize>,
+    /// Include prereleases like `1.2.0-canary.1`
+    pub prereleases: bool,
+    /// Only versions published on or after this day (`YYYY-MM-DD`)
+    pub since: Option<String>,
+}
+
+impl RemoteFilter {
+    fn matches(&self, release: &bun::Release) -> bool {
+        if release.version.is_prerelease() && !self.prereleases {
+            return false;
+        }
+
+        if let Some(prefix) = &self.prefix {
+            let prefix = prefix.trim().trim_start_matches('v');
+            let version = release.version.to_string();
+            let matches_prefix = version == prefix
+                || version.starts_with(&format!("{prefix}."))
+                || version.starts_with(&format!("{prefix}-"));
+            if !matches_prefix {
+                return false;
+            }
+        }
+
+        // Dates in the same format compare like strings
+        match (&self.since, release.published_on()) {
+            (Some(since), Some(published_on)) => published_on >= since.as_str(),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Filters releases sorted newest first.
+    fn apply<'a>(&self, releases: &'a [bun::Release]) -> Vec<&'a bun::Release> {
+        releases
+            .iter()
+            .filter(|release| self.matches(release))
+            .take(self.latest.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Checks that `--since` is a date like `2024-07-13`.
+pub fn parse_date(input: &str) -> Result<String, String> {
+    let date = input.trim();
+    let parts: Vec<&str> = date.split('-').collect();
+    let is_date = matches!(
+        parts.as_slice(),
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2
+    ) && parts
+        .iter()
+        .all(|part| part.chars().all(|c| c.is_ascii_digit()));
+
+    if is_date {
+        Ok(date.to_string())
+    } else {
+        Err(format!("expected a date like 2024-07-13, got \"{date}\""))
+    }
+}
+
+pub async fn list_remote(filter: &RemoteFilter, refresh: bool) -> Result<()> {
+    let releases = bun::get_releases(refresh)
+        .await
+        .map_err(|e| anyhow!("Failed to get remote version list: {}", e))?;
+
+    let installed = installed_versions_in(&FOLDER_VERSION_BASE).unwrap_or_default();
+    let active_version = bun::get_active_version().await.parse::<BunVersion>().ok();
+    let active_color: DynColors = "#eea990".parse().unwrap();
+    let active_style = owo_colors::Style::new().color(active_color).bold();
+    let architecture = os::get_architecture();
+
+    let releases = filter.apply(&releases);
+    if releases.is_empty() {
+        println!("No remote versions match");
+        return Ok(());
+    }
+
+    let version_width = releases
+        .iter()
+        .map(|release| release.version.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    for release in releases {
+        let version = format!("v{:<version_width$}", release.version.to_string());
+        let mut row = format!("{version}  {:<10}", release.published_on().unwrap_or(""));
+
+        if Some(&release.version) == active_version.as_ref() {
+            row = format!(
+                "{} {}",
+                row.style(active_style),
+                "(active)".style(active_style)
+            );
+        } else if installed.contains(&release.version) {
+            row.push_str(" (installed)");
+        }
+        if !release.has_build_for(&architecture) {
+            row.push_str(&format!(" (no {} build)", architecture));
+        }
+
+        println!("  {}", row.trim_end());
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bun::tests::{signed_mirror, signing_fixture};
     use std::fs;
     use std::path::PathBuf;
     use tempfile::TempDir;
@@ -260,7 +894,7 @@ mod tests {
         }
 
         fn create_mock_version(&self, version: &str) -> PathBuf {
-            let version_dir = self.versions_dir.join(version);
+            let version_dir = version_dir_in(&self.versions_dir, &version.parse().unwrap());
             fs::create_dir_all(&version_dir).unwrap();
 
             let bun_bin = version_dir.join(BUN_BIN_NAME);
@@ -290,12 +924,37 @@ mod tests {
         }
     }
 
+    fn normalize(version: &str) -> String {
+        version.parse::<BunVersion>().unwrap().to_string()
+    }
+
     #[test]
     fn test_version_normalization() {
-        assert_eq!(normalize_version("v1.0.0"), "1.0.0");
-        assert_eq!(normalize_version("1.0.0"), "1.0.0");
-        assert_eq!(normalize_version("v1.2.3"), "1.2.3");
-        assert_eq!(normalize_version("v2.0.0-beta"), "2.0.0-beta");
+        assert_eq!(normalize("v1.0.0"), "1.0.0");
+        assert_eq!(normalize("1.0.0"), "1.0.0");
+        assert_eq!(normalize("v1.2.3"), "1.2.3");
+        assert_eq!(normalize("v2.0.0-beta"), "2.0.0-beta");
+        assert_eq!(normalize("v1.1.0-dev"), "1.1.0-dev");
+        assert!("not-a-version".parse::<BunVersion>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_range_resolves_against_installed_versions() {
+        let env = TestEnv::new();
+
+        for version in ["1.0.36", "1.1.0", "1.1.20", "1.2.0"] {
+            env.create_mock_version(version);
+        }
+        fs::write(env.versions_dir.join("1.3.0.zip"), "partial download").unwrap();
+
+        let installed = installed_versions_in(&env.versions_dir).unwrap();
+        assert_eq!(installed.len(), 4, "Only version directories should count");
+
+        let request = VersionRequest::parse("~1.1").unwrap();
+        assert_eq!(
+            request.newest_match(&installed).map(|v| v.to_string()),
+            Some("1.1.20".to_string())
+        );
     }
 
     #[test]
@@ -387,6 +1046,167 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_sessions_switch_independently() {
+        let env = TestEnv::new();
+        let sessions_dir = env._temp_dir.path().join(".bum").join("sessions");
+
+        let v1_bun = env.create_mock_version("1.0.0").join(BUN_BIN_NAME);
+        let v2_bun = env.create_mock_version("2.0.0").join(BUN_BIN_NAME);
+
+        let first = create_session_in(&sessions_dir, std::process::id())
+            .await
+            .unwrap();
+        let second = create_session_in(&sessions_dir, std::process::id())
+            .await
+            .unwrap();
+        assert_ne!(first, second, "Each shell should get its own session");
+
+        let first_bun = first.join("bin").join(BUN_BIN_NAME);
+        let second_bun = second.join("bin").join(BUN_BIN_NAME);
+
+        link_bun_to(v1_bun.clone(), first_bun.clone())
+            .await
+            .unwrap();
+        link_bun_to(v2_bun, second_bun.clone()).await.unwrap();
+        assert_eq!(fs::read_to_string(&first_bun).unwrap(), "mock bun 1.0.0");
+        assert_eq!(fs::read_to_string(&second_bun).unwrap(), "mock bun 2.0.0");
+
+        // Switching again replaces the session's bun without touching the version
+        link_bun_to(v1_bun.clone(), second_bun.clone())
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&second_bun).unwrap(), "mock bun 1.0.0");
+        assert_eq!(fs::read_to_string(&v1_bun).unwrap(), "mock bun 1.0.0");
+        assert!(
+            env.read_active_bun().is_none(),
+            "Global bun should be untouched"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sessions_of_exited_shells_are_pruned() {
+        let temp_dir = TempDir::new().unwrap();
+        let sessions_dir = temp_dir.path().join("sessions");
+        for session in [
+            "100_1700000000000_0",
+            "200_1700000000000_0",
+            "200_1700000000000_1",
+        ] {
+            fs::create_dir_all(sessions_dir.join(session).join("bin")).unwrap();
+        }
+        fs::create_dir_all(sessions_dir.join("not-a-session")).unwrap();
+
+        prune_sessions_in(&sessions_dir, |pid| pid == 100)
+            .await
+            .unwrap();
+
+        let mut left: Vec<String> = fs::read_dir(&sessions_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["100_1700000000000_0", "not-a-session"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_new_session_prunes_exited_shells() {
+        let temp_dir = TempDir::new().unwrap();
+        let sessions_dir = temp_dir.path().join("sessions");
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let exited_pid = child.id();
+        child.wait().unwrap();
+        let stale = sessions_dir.join(format!("{exited_pid}_1700000000000_0"));
+        fs::create_dir_all(stale.join("bin")).unwrap();
+
+        let session = create_session_in(&sessions_dir, std::process::id())
+            .await
+            .unwrap();
+
+        assert!(!stale.exists());
+        assert!(session.join("bin").is_dir());
+        assert!(process_is_running(std::process::id()));
+        assert!(!process_is_running(exited_pid));
+    }
+
+    #[test]
+    fn test_exec_missing_command_fails() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let error = exec_in(temp_dir.path(), "bum-test-no-such-command", &[]).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Failed to run bum-test-no-such-command"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_exec_puts_version_first_on_path() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let output = command_with_bin_dir(
+            temp_dir.path(),
+            "sh",
+            &["-c".to_string(), "echo \"$PATH\"".to_string()],
+        )
+        .unwrap()
+        .output()
+        .unwrap();
+
+        let path = String::from_utf8(output.stdout).unwrap();
+        let first = path.trim().split(':').next().unwrap();
+        assert_eq!(Path::new(first), temp_dir.path());
+    }
+
+    /// Runs itself again as a child that `exec`s into a command, since a
+    /// successful `exec_in` replaces the process that calls it.
+    #[cfg(not(windows))]
+    #[test]
+    fn test_exec_forwards_exit_code() {
+        const CHILD_ENV_VAR: &str = "BUM_TEST_EXEC_BIN_DIR";
+
+        if let Some(bin_dir) = std::env::var_os(CHILD_ENV_VAR) {
+            let args = ["-c".to_string(), "exit 3".to_string()];
+            let _ = exec_in(Path::new(&bin_dir), "sh", &args);
+            std::process::exit(100);
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "commands::tests::test_exec_forwards_exit_code",
+                "--test-threads=1",
+            ])
+            .env(CHILD_ENV_VAR, temp_dir.path())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_use_fails_on_checksum_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = InstallContext::for_mirror(
+            &signed_mirror(temp_dir.path(), "tampered bun zip"),
+            temp_dir.path(),
+            Some(&signing_fixture("trusted-key.asc")),
+        );
+        let versions_dir = temp_dir.path().join("bun-versions");
+
+        let error = use_bun_in(&ctx, "1.2.3", &versions_dir).await.unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Checksum mismatch for bun-linux-x64.zip"));
+        assert!(installed_versions_in(&versions_dir).unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_remove_version() {
         let env = TestEnv::new();
@@ -439,26 +1259,22 @@ mod tests {
     async fn test_list_versions_sorted() {
         let env = TestEnv::new();
 
-        let versions = vec!["1.2.0", "1.0.0", "1.1.0", "2.0.0"];
+        let versions = vec!["1.2.0", "1.0.0", "1.10.0", "1.1.0", "2.0.0", "1.9.0"];
         for version in &versions {
             env.create_mock_version(version);
         }
 
-        let mut found_versions = Vec::new();
-        let entries = fs::read_dir(env.versions_dir).unwrap();
-        for entry in entries {
-            let entry = entry.unwrap();
-            let version = entry.file_name().to_string_lossy().to_string();
-            found_versions.push(version);
-        }
-
+        let mut found_versions = installed_versions_in(&env.versions_dir).unwrap();
         found_versions.sort();
         found_versions.reverse();
 
         assert_eq!(
-            found_versions,
-            vec!["2.0.0", "1.2.0", "1.1.0", "1.0.0"],
-            "Versions should be sorted descending"
+            found_versions
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>(),
+            vec!["2.0.0", "1.10.0", "1.9.0", "1.2.0", "1.1.0", "1.0.0"],
+            "Versions should be sorted descending by semver"
         );
     }
 
@@ -491,7 +1307,28 @@ mod tests {
         // Test with v prefix
         fs::write(&bumrc_path, "v1.0.0").unwrap();
         let content = fs::read_to_string(&bumrc_path).unwrap();
-        assert_eq!(normalize_version(content.trim()), "1.0.0");
+        assert_eq!(normalize(content.trim()), "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_default_version_persists() {
+        let env = TestEnv::new();
+        let default_path = env._temp_dir.path().join(".bum").join("default");
+
+        assert_eq!(read_default_from(&default_path).unwrap(), None);
+
+        write_default_to("1.1.20", &default_path).await.unwrap();
+        assert_eq!(
+            read_default_from(&default_path).unwrap(),
+            Some("1.1.20".to_string())
+        );
+
+        write_default_to("1.2", &default_path).await.unwrap();
+        assert_eq!(
+            read_default_from(&default_path).unwrap(),
+            Some("1.2".to_string()),
+            "Setting a new default should replace the old one"
+        );
     }
 
     #[test]
@@ -522,6 +1359,105 @@ mod tests {
         );
     }
 
+    fn release(version: &str, published_on: &str) -> bun::Release {
+        bun::Release {
+            version: version.parse().unwrap(),
+            published_at: Some(format!("{published_on}T12:00:00Z")),
+            assets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_remote_filter() {
+        let releases = vec![
+            release("1.2.0-canary.1", "2024-09-01"),
+            release("1.10.0", "2024-08-01"),
+            release("1.1.2", "2024-07-01"),
+            release("1.1.1", "2024-06-01"),
+            release("1.1.0", "2024-05-01"),
+        ];
+        let versions = |filter: RemoteFilter| -> Vec<String> {
+            filter
+                .apply(&releases)
+                .iter()
+                .map(|release| release.version.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            versions(RemoteFilter::default()),
+            ["1.10.0", "1.1.2", "1.1.1", "1.1.0"]
+        );
+        assert_eq!(
+            versions(RemoteFilter {
+                prefix: Some("v1.1".to_string()),
+                latest: Some(2),
+                ..Default::default()
+            }),
+            ["1.1.2", "1.1.1"]
+        );
+        assert_eq!(
+            versions(RemoteFilter {
+                prereleases: true,
+                since: Some("2024-08-01".to_string()),
+                ..Default::default()
+            }),
+            ["1.2.0-canary.1", "1.10.0"]
+        );
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date(" 2024-07-13").unwrap(), "2024-07-13");
+        assert!(parse_date("2024-7-13").is_err());
+        assert!(parse_date("13.07.2024").is_err());
+    }
+
+    #[test]
+    fn test_on_cd_resolves_against_installed_versions_only() {
+        let env = TestEnv::new();
+        let project = env._temp_dir.path().join("project");
+        fs::create_dir_all(project.join(".git")).unwrap();
+        let default_path = env._temp_dir.path().join(".bum").join("default");
+        env.create_mock_version("1.1.18");
+        env.create_mock_version("1.1.20");
+
+        // No pin and no default: nothing to do
+        assert_eq!(
+            pinned_version_in(&project, &env.versions_dir, &default_path).unwrap(),
+            None
+        );
+
+        fs::write(project.join(".bumrc"), "1.1").unwrap();
+        assert_eq!(
+            pinned_version_in(&project, &env.versions_dir, &default_path).unwrap(),
+            Some(PinnedVersion::Installed {
+                version: "1.1.20".parse().unwrap(),
+                source: project.join(".bumrc").display().to_string(),
+            })
+        );
+
+        // A newer remote version doesn't matter, only what's installed
+        fs::write(project.join(".bumrc"), "1.2").unwrap();
+        assert_eq!(
+            pinned_version_in(&project, &env.versions_dir, &default_path).unwrap(),
+            Some(PinnedVersion::Missing {
+                version: "1.2".to_string(),
+                source: project.join(".bumrc").display().to_string(),
+            })
+        );
+
+        fs::remove_file(project.join(".bumrc")).unwrap();
+        fs::write(&default_path, "1.1.18").unwrap();
+        assert_eq!(
+            pinned_version_in(&project, &env.versions_dir, &default_path).unwrap(),
+            Some(PinnedVersion::Installed {
+                version: "1.1.18".parse().unwrap(),
+                source: "the default version".to_string(),
+            })
+        );
+    }
+
     /// Integration test that verifies the npm package works end-to-end.
     /// This test:
     /// 1. Builds the native binding
@@ -0,0 +1,203 @@
+use crate::commands::CONFIG_PATH;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_DOWNLOAD_MIRROR: &str = "https://github.com/oven-sh/bun/releases/download";
+const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+const DEFAULT_DOWNLOAD_RETRIES: u32 = 4;
+const DEFAULT_CACHE_SIZE_LIMIT_MB: u64 = 1024;
+const DEFAULT_REMOTE_VERSIONS_TTL_MINUTES: u64 = 60;
+
+/// Overrides `download_mirror` from the config file.
+const DOWNLOAD_MIRROR_ENV_VAR: &str = "BUM_DOWNLOAD_MIRROR";
+/// Overrides `github_api_url` from the config file.
+const GITHUB_API_URL_ENV_VAR: &str = "BUM_GITHUB_API_URL";
+/// Overrides `download_retries` from the config file.
+const DOWNLOAD_RETRIES_ENV_VAR: &str = "BUM_DOWNLOAD_RETRIES";
+/// Overrides `cache_size_limit_mb` from the config file.
+const CACHE_SIZE_LIMIT_ENV_VAR: &str = "BUM_CACHE_SIZE_LIMIT_MB";
+/// Overrides `remote_versions_ttl_minutes` from the config file.
+const REMOTE_VERSIONS_TTL_ENV_VAR: &str = "BUM_REMOTE_VERSIONS_TTL_MINUTES";
+
+/// Settings read from `~/.bum/config.json`. Every key is optional, and
+/// environment variables take precedence over the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    download_mirror: Option<String>,
+    github_api_url: Option<String>,
+    download_retries: Option<u32>,
+    cache_size_limit_mb: Option<u64>,
+    remote_versions_ttl_minutes: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Base URL of the release assets, laid out like GitHub releases:
+    /// `<download_mirror>/bun-v<version>/<asset>`.
+    pub download_mirror: String,
+    /// Base URL of the GitHub API used to list versions. A `file://` URL is a
+    /// directory laid out like `download_mirror`, and its `bun-v*`
+    /// directories are the available versions.
+    pub github_api_url: String,
+    /// How many times a failed download is retried before giving up.
+    pub download_retries: u32,
+    /// Size in bytes the archive cache is trimmed to, oldest entries first.
+    pub cache_size_limit: u64,
+    /// How long the list of remote versions is used before asking the API
+    /// whether it changed.
+    pub remote_versions_ttl: Duration,
+}
+
+impl Config {
+    pub fn load() -> Result<Config> {
+        Config::load_from(&CONFIG_PATH, |name| std::env::var(name).ok())
+    }
+
+    fn load_from(config_path: &Path, env_var: impl Fn(&str) -> Option<String>) -> Result<Config> {
+        let file = match std::fs::read_to_string(config_path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| anyhow!("Invalid config file {}: {}", config_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ConfigFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let setting = |env_name: &str, from_file: Option<String>, default: &str| {
+            let url = env_var(env_name)
+                .filter(|value| !value.trim().is_empty())
+                .or(from_file)
+                .unwrap_or_else(|| default.to_string());
+            url.trim().trim_end_matches('/').to_string()
+        };
+
+        let download_retries = number_setting(
+            DOWNLOAD_RETRIES_ENV_VAR,
+            env_var(DOWNLOAD_RETRIES_ENV_VAR),
+            file.download_retries,
+            DEFAULT_DOWNLOAD_RETRIES,
+        )?;
+        let cache_size_limit_mb = number_setting(
+            CACHE_SIZE_LIMIT_ENV_VAR,
+            env_var(CACHE_SIZE_LIMIT_ENV_VAR),
+            file.cache_size_limit_mb,
+            DEFAULT_CACHE_SIZE_LIMIT_MB,
+        )?;
+        let remote_versions_ttl_minutes = number_setting(
+            REMOTE_VERSIONS_TTL_ENV_VAR,
+            env_var(REMOTE_VERSIONS_TTL_ENV_VAR),
+            file.remote_versions_ttl_minutes,
+            DEFAULT_REMOTE_VERSIONS_TTL_MINUTES,
+        )?;
+
+        Ok(Config {
+            download_mirror: setting(
+                DOWNLOAD_MIRROR_ENV_VAR,
+                file.download_mirror,
+                DEFAULT_DOWNLOAD_MIRROR,
+            ),
+            github_api_url: setting(
+                GITHUB_API_URL_ENV_VAR,
+                file.github_api_url,
+                DEFAULT_GITHUB_API_URL,
+            ),
+            download_retries,
+            cache_size_limit: cache_size_limit_mb.saturating_mul(1024 * 1024),
+            remote_versions_ttl: Duration::from_secs(
+                remote_versions_ttl_minutes.saturating_mul(60),
+            ),
+        })
+    }
+}
+
+fn number_setting<T: FromStr>(
+    env_name: &str,
+    env_value: Option<String>,
+    from_file: Option<T>,
+    default: T,
+) -> Result<T> {
+    match env_value {
+        Some(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Invalid {}=\"{}\", expected a number", env_name, value)),
+        _ => Ok(from_file.unwrap_or(default)),
+    }
+}
+
+/// The local path of a `file://` URL, or `None` for any other URL.
+pub fn file_url_path(url: &str) -> Option<PathBuf> {
+    url.strip_prefix("file://").map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn load(config: Option<&str>, env: &[(&str, &str)]) -> Config {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        if let Some(config) = config {
+            std::fs::write(&config_path, config).unwrap();
+        }
+
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Config::load_from(&config_path, |name| env.get(name).cloned()).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_to_github() {
+        let config = load(None, &[]);
+
+        assert_eq!(config.download_mirror, DEFAULT_DOWNLOAD_MIRROR);
+        assert_eq!(config.github_api_url, DEFAULT_GITHUB_API_URL);
+        assert_eq!(config.download_retries, DEFAULT_DOWNLOAD_RETRIES);
+        assert_eq!(config.cache_size_limit, 1024 * 1024 * 1024);
+        assert_eq!(config.remote_versions_ttl, Duration::from_secs(60 * 60));
+    }
+
+    #[test]
+    fn test_env_overrides_config_file() {
+        let config_file = r#"{
+            "download_mirror": "https://proxy.internal/bun/",
+            "github_api_url": "https://proxy.internal/github-api",
+            "download_retries": 10,
+            "cache_size_limit_mb": 100,
+            "remote_versions_ttl_minutes": 5
+        }"#;
+
+        let config = load(Some(config_file), &[]);
+        assert_eq!(config.download_mirror, "https://proxy.internal/bun");
+        assert_eq!(config.github_api_url, "https://proxy.internal/github-api");
+        assert_eq!(config.download_retries, 10);
+        assert_eq!(config.cache_size_limit, 100 * 1024 * 1024);
+        assert_eq!(config.remote_versions_ttl, Duration::from_secs(5 * 60));
+
+        let config = load(
+            Some(config_file),
+            &[
+                (DOWNLOAD_MIRROR_ENV_VAR, "file:///srv/bun-releases/"),
+                (DOWNLOAD_RETRIES_ENV_VAR, "0"),
+            ],
+        );
+        assert_eq!(config.download_mirror, "file:///srv/bun-releases");
+        assert_eq!(config.github_api_url, "https://proxy.internal/github-api");
+        assert_eq!(config.download_retries, 0);
+    }
+
+    #[test]
+    fn test_file_url_path() {
+        assert_eq!(
+            file_url_path("file:///srv/bun-releases"),
+            Some(PathBuf::from("/srv/bun-releases"))
+        );
+        assert_eq!(file_url_path("https://github.com"), None);
+    }
+}
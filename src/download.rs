@@ -0,0 +1,361 @@
+use crate::progress::DownloadProgress;
+use anyhow::{anyhow, bail, Result};
+use indicatif::HumanBytes;
+use reqwest::header::{ETAG, IF_RANGE, LAST_MODIFIED, RANGE, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::{AsyncWriteExt, BufWriter},
+};
+
+/// Upper bound for the wait between two attempts, including `Retry-After`.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Returned by `download_file` when the server has no such file, which is
+/// never retried.
+#[derive(Debug)]
+pub struct NotFound;
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Not found")
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// How often and how patiently a failed download is retried.
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(retries: u32) -> Self {
+        RetryPolicy {
+            retries,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Exponential backoff: the base delay, doubled after every attempt.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+enum Failure {
+    /// Connection errors, 5xx and 429, which may go away on their own.
+    Retry {
+        error: anyhow::Error,
+        after: Option<Duration>,
+    },
+    Fatal(anyhow::Error),
+}
+
+fn part_path_of(to_path: &Path) -> PathBuf {
+    let mut file_name = to_path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".part");
+    to_path.with_file_name(file_name)
+}
+
+/// Where the `ETag` or `Last-Modified` of the file being downloaded to
+/// `part_path` is kept, to only resume when the server still has that file.
+fn validator_path_of(part_path: &Path) -> PathBuf {
+    let mut file_name = part_path
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_default();
+    file_name.push(".validator");
+    part_path.with_file_name(file_name)
+}
+
+/// What `If-Range` can be sent with: a strong `ETag`, or `Last-Modified`.
+fn validator_of(response: &Response) -> Option<String> {
+    let header = |name| response.headers().get(name)?.to_str().ok();
+
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+        .map(|validator| validator.to_string())
+}
+
+/// Downloads `url` to `to_path`, retrying transient failures with
+/// exponential backoff. Bytes are written to `<to_path>.part` first, and a
+/// retry (or the next run, once the retries are used up) continues from
+/// there with a Range request instead of starting over. The request carries
+/// `If-Range`, so a file that changed on the server is downloaded again.
+pub async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    to_path: &Path,
+    policy: &RetryPolicy,
+) -> Result<()> {
+    let part_path = part_path_of(to_path);
+    let mut attempt = 0;
+
+    loop {
+        match try_download(client, url, &part_path).await {
+            Ok(()) => {
+                fs::rename(&part_path, to_path).await?;
+                let _ = fs::remove_file(validator_path_of(&part_path)).await;
+                return Ok(());
+            }
+            Err(Failure::Fatal(error)) => return Err(error),
+            Err(Failure::Retry { error, after }) if attempt < policy.retries => {
+                let delay = after
+                    .map(|after| after.min(MAX_RETRY_DELAY))
+                    .unwrap_or_else(|| policy.delay(attempt));
+                attempt += 1;
+                eprintln!(
+                    "Download failed ({}), retrying in {}s ({}/{})...",
+                    error,
+                    delay.as_secs_f32(),
+                    attempt,
+                    policy.retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(Failure::Retry { error, .. }) => {
+                bail!(
+                    "Download failed ({}), giving up after {} retries. Run the command again to resume it",
+                    error,
+                    policy.retries
+                );
+            }
+        }
+    }
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+async fn try_download(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+) -> Result<(), Failure> {
+    let fatal = |error: std::io::Error| Failure::Fatal(error.into());
+    let retry = |error: reqwest::Error| Failure::Retry {
+        error: error.into(),
+        after: None,
+    };
+
+    let validator_path = validator_path_of(part_path);
+    // Without a validator there's no telling whether the partial file is
+    // still part of the file on the server
+    let validator = fs::read_to_string(&validator_path).await.ok();
+    let offset = match validator {
+        Some(_) => fs::metadata(part_path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    let mut request = client.get(url);
+    if let Some(validator) = validator.filter(|_| offset > 0) {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, validator);
+    }
+    let mut response = request.send().await.map_err(retry)?;
+
+    let resumed = match response.status() {
+        StatusCode::PARTIAL_CONTENT => true,
+        // Also the answer to a resume when the file changed in the meantime
+        StatusCode::OK => false,
+        // The partial file doesn't fit the file on the server (anymore)
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            fs::remove_file(part_path).await.map_err(fatal)?;
+            return Err(Failure::Retry {
+                error: anyhow!("the partial download doesn't match"),
+                after: Some(Duration::ZERO),
+            });
+        }
+        StatusCode::NOT_FOUND => return Err(Failure::Fatal(NotFound.into())),
+        status if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() => {
+            return Err(Failure::Retry {
+                error: anyhow!("HTTP {}", status),
+                after: retry_after(&response),
+            });
+        }
+        status => {
+            return Err(Failure::Fatal(anyhow!(
+                "HTTP request was not successful: {}",
+                status
+            )));
+        }
+    };
+
+    let file = if resumed {
+        eprintln!("Resuming download from {}", HumanBytes(offset));
+        OpenOptions::new().append(true).open(part_path).await
+    } else {
+        match validator_of(&response) {
+            Some(validator) => fs::write(&validator_path, validator).await.map_err(fatal)?,
+            None => {
+                let _ = fs::remove_file(&validator_path).await;
+            }
+        }
+        File::create(part_path).await
+    }
+    .map_err(fatal)?;
+
+    let already_downloaded = if resumed { offset } else { 0 };
+    let mut writer = BufWriter::new(file);
+    let mut progress = DownloadProgress::new(
+        response
+            .content_length()
+            .map(|len| len + already_downloaded),
+    );
+    progress.resume_from(already_downloaded);
+
+    // Stream the body to disk instead of holding the whole zip in memory
+    let streamed = loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                writer.write_all(&chunk).await.map_err(fatal)?;
+                progress.advance(chunk.len());
+            }
+            Ok(None) => break Ok(()),
+            Err(error) => break Err(retry(error)),
+        }
+    };
+
+    // Keep what arrived before a failure, so the next attempt can resume
+    writer.flush().await.map_err(fatal)?;
+    streamed?;
+    progress.finish();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::serve;
+    use tempfile::TempDir;
+
+    fn no_delay(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumes_interrupted_download() {
+        let temp_dir = TempDir::new().unwrap();
+        let to_path = temp_dir.path().join("1.1.0.zip");
+        let (base, server) = serve(&[
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            // The connection drops halfway through the body
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nETag: \"v1\"\r\nConnection: close\r\n\r\nhello ",
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\nConnection: close\r\n\r\nworld",
+        ]);
+
+        let url = format!("{base}/bun-linux-x64.zip");
+        download_file(&reqwest::Client::new(), &url, &to_path, &no_delay(3))
+            .await
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(!requests[1].contains("range:"));
+        assert!(requests[2].contains("range: bytes=6-"));
+        assert!(requests[2].contains("if-range: \"v1\""));
+        assert_eq!(std::fs::read_to_string(&to_path).unwrap(), "hello world");
+        assert!(!part_path_of(&to_path).exists());
+        assert!(!validator_path_of(&part_path_of(&to_path)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_changed_file_is_downloaded_again() {
+        let temp_dir = TempDir::new().unwrap();
+        let to_path = temp_dir.path().join("1.1.0.zip");
+        let (base, server) = serve(&[
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nLast-Modified: Mon, 01 Apr 2024 12:00:00 GMT\r\nConnection: close\r\n\r\nhello ",
+            // If-Range didn't match, so the whole new file comes back
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\nHELLO WORLD",
+        ]);
+
+        let url = format!("{base}/bun-linux-x64.zip");
+        download_file(&reqwest::Client::new(), &url, &to_path, &no_delay(1))
+            .await
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("if-range: mon, 01 apr 2024 12:00:00 gmt"));
+        assert_eq!(std::fs::read_to_string(&to_path).unwrap(), "HELLO WORLD");
+    }
+
+    #[tokio::test]
+    async fn test_partial_download_without_validator_starts_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let to_path = temp_dir.path().join("1.1.0.zip");
+        std::fs::write(part_path_of(&to_path), "stale").unwrap();
+        let (base, server) = serve(&[
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        ]);
+
+        let url = format!("{base}/bun-linux-x64.zip");
+        download_file(&reqwest::Client::new(), &url, &to_path, &no_delay(0))
+            .await
+            .unwrap();
+
+        assert!(!server.join().unwrap()[0].contains("range:"));
+        assert_eq!(std::fs::read_to_string(&to_path).unwrap(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_retry_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let to_path = temp_dir.path().join("1.1.0.zip");
+        let (base, server) = serve(&[
+            "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+
+        let url = format!("{base}/bun-linux-x64.zip");
+        let error = download_file(&reqwest::Client::new(), &url, &to_path, &no_delay(1))
+            .await
+            .unwrap_err();
+
+        assert_eq!(server.join().unwrap().len(), 2);
+        assert!(error.to_string().contains("giving up after 1 retries"));
+        assert!(!to_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_not_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let to_path = temp_dir.path().join("1.1.0.zip");
+        let (base, server) =
+            serve(&["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"]);
+
+        let url = format!("{base}/bun-linux-x64.zip");
+        let error = download_file(&reqwest::Client::new(), &url, &to_path, &no_delay(3))
+            .await
+            .unwrap_err();
+
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert!(error.is::<NotFound>());
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_limit() {
+        let policy = RetryPolicy::new(10);
+
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(9), MAX_RETRY_DELAY);
+    }
+}
@@ -1,16 +1,36 @@
 #![allow(clippy::all, clippy::pedantic)]
 
 use anyhow::Result;
+use bun::Interrupted;
 use clap::Parser;
 use napi_derive::napi;
 
 pub mod bun;
+mod cache;
 pub mod commands;
+mod config;
+mod download;
 mod os;
+mod progress;
+mod remote_versions;
+pub mod shell;
+pub mod shim;
+#[cfg(test)]
+mod test_server;
 mod utils;
+mod verify;
+mod version;
+mod version_file;
 
-use commands::{list, list_remote, remove, use_bumrc, use_bun};
+use commands::{
+    cache_clean, cache_list, cache_size, env, exec, install_from_file, list, list_remote,
+    parse_date, pin, print_default, remove, set_default, use_bumrc, use_bun, use_on_cd,
+    RemoteFilter,
+};
+use os::Variant;
 use owo_colors::{DynColors, OwoColorize};
+use shell::Shell;
+use std::path::PathBuf;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -20,6 +40,10 @@ pub struct Cli {
     #[clap(short, long)]
     version: bool,
 
+    /// Which Bun build to install on x64, detected from the CPU by default
+    #[clap(long, global = true, value_enum)]
+    variant: Option<Variant>,
+
     #[clap(subcommand)]
     pub command: Option<Command>,
 }
@@ -28,19 +52,41 @@ pub struct Cli {
 pub enum Command {
     Default(DefaultCommand),
     Use(UseCommand),
+    Install(InstallCommand),
     Remove(RemoveCommand),
     List(ListCommand),
     ListRemote(ListRemoteCommand),
+    Pin(PinCommand),
+    Env(EnvCommand),
+    Exec(ExecCommand),
+    Shim(ShimCommand),
+    Cache(CacheCommand),
 }
 
 #[derive(Parser)]
 pub struct DefaultCommand {
-    version: String,
+    version: Option<String>,
 }
 
 #[derive(Parser)]
 pub struct UseCommand {
     version: Option<String>,
+
+    /// Switch to the pinned version only if it isn't active yet, without
+    /// touching the network when it's installed (used by the shell hook)
+    #[clap(long, conflicts_with = "version")]
+    on_cd: bool,
+}
+
+#[derive(Parser)]
+pub struct InstallCommand {
+    /// A Bun release zip on disk, e.g. bun-linux-x64.zip
+    #[clap(long)]
+    from_file: PathBuf,
+
+    /// The version the zip has to hold, checked against the binary
+    #[clap(long)]
+    version: Option<String>,
 }
 
 #[derive(Parser)]
@@ -48,10 +94,85 @@ pub struct RemoveCommand {
     version: String,
 }
 
+#[derive(Parser)]
+pub struct PinCommand {
+    version: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct EnvCommand {
+    /// Also print a hook that runs `bum use` whenever the directory changes
+    #[clap(long)]
+    use_on_cd: bool,
+
+    /// The shell to print setup code for, detected from $SHELL by default
+    #[clap(long, value_enum)]
+    shell: Option<Shell>,
+}
+
+#[derive(Parser)]
+pub struct ExecCommand {
+    version: String,
+
+    /// The command to run, after `--`
+    #[clap(last = true, required = true)]
+    command: Vec<String>,
+}
+
+#[derive(Parser)]
+pub struct ShimCommand {
+    #[clap(subcommand)]
+    action: ShimAction,
+}
+
+#[derive(Parser)]
+pub enum ShimAction {
+    /// Replace the active bun with a shim that runs the version pinned for
+    /// the current directory, or the default version
+    Install,
+    /// Remove the shim
+    Uninstall,
+}
+
+#[derive(Parser)]
+pub struct CacheCommand {
+    #[clap(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Parser)]
+pub enum CacheAction {
+    /// Show the cached release archives
+    List,
+    /// Remove every cached release archive
+    Clean,
+    /// Show how much space the cache takes
+    Size,
+}
+
 #[derive(Parser)]
 pub struct ListCommand {}
 #[derive(Parser)]
-pub struct ListRemoteCommand {}
+pub struct ListRemoteCommand {
+    /// Only show versions starting with this, e.g. 1.1
+    prefix: Option<String>,
+
+    /// Only show the newest N versions
+    #[clap(long, value_name = "N")]
+    latest: Option<usize>,
+
+    /// Also show prereleases
+    #[clap(long)]
+    prereleases: bool,
+
+    /// Only show versions released on or after this date, e.g. 2024-07-13
+    #[clap(long, value_name = "DATE", value_parser = parse_date)]
+    since: Option<String>,
+
+    /// Fetch the list from GitHub even when the cached one is recent
+    #[clap(long)]
+    refresh: bool,
+}
 
 async fn run_commands(used_command: Option<Command>) -> Result<()> {
     if used_command.is_none() {
@@ -59,23 +180,48 @@ async fn run_commands(used_command: Option<Command>) -> Result<()> {
         return Ok(());
     }
     match used_command.unwrap() {
-        Command::Default(_args) => {
-            println!("This feature will be implemented in the future.");
-        }
+        Command::Default(args) => match args.version {
+            Some(version) => set_default(&version).await?,
+            None => print_default().await?,
+        },
         Command::Remove(args) => {
-            remove(&args.version).await;
+            remove(&args.version).await?;
         }
         Command::Use(args) => match args.version {
             Some(version) => {
                 use_bun(&version).await?;
             }
+            None if args.on_cd => {
+                use_on_cd().await?;
+            }
             None => {
                 use_bumrc().await?;
             }
         },
+        Command::Install(args) => {
+            install_from_file(&args.from_file, args.version.as_deref()).await?
+        }
+        Command::Pin(args) => pin(args.version.as_deref()).await?,
+        Command::Env(args) => env(args.use_on_cd, args.shell).await?,
+        Command::Exec(args) => exec(&args.version, &args.command).await?,
+        Command::Shim(args) => match args.action {
+            ShimAction::Install => shim::install().await?,
+            ShimAction::Uninstall => shim::uninstall()?,
+        },
+        Command::Cache(args) => match args.action {
+            CacheAction::List => cache_list().await?,
+            CacheAction::Clean => cache_clean().await?,
+            CacheAction::Size => cache_size().await?,
+        },
         Command::List(_) => list().await?,
-        Command::ListRemote(_) => {
-            list_remote().await;
+        Command::ListRemote(args) => {
+            let filter = RemoteFilter {
+                prefix: args.prefix,
+                latest: args.latest,
+                prereleases: args.prereleases,
+                since: args.since,
+            };
+            list_remote(&filter, args.refresh).await?
         }
     }
 
@@ -121,15 +267,21 @@ pub fn run(args: Vec<String>) -> napi::Result<()> {
 
     match cli {
         Ok(result) => {
+            if let Some(variant) = result.variant {
+                os::set_variant(variant);
+            }
+
             if result.version {
                 println!("{}", VERSION);
             } else {
-                rt.block_on(async {
-                    if let Err(e) = run_commands(result.command).await {
-                        eprintln!("An error occurred during the execution: {e}");
-                        std::process::exit(1);
+                if let Err(e) = rt.block_on(run_commands(result.command)) {
+                    // Node exits on its own, see bin.js
+                    if e.is::<Interrupted>() {
+                        return Err(napi::Error::new(napi::Status::Cancelled, e.to_string()));
                     }
-                });
+                    eprintln!("An error occurred during the execution: {e}");
+                    std::process::exit(1);
+                }
             }
         }
         Err(e) => {
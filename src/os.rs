@@ -1,13 +1,272 @@
+use clap::ValueEnum;
 use std::env::consts::{ARCH, OS};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Which build of Bun to install on x64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Variant {
+    /// The regular build, which needs a CPU with AVX2
+    Default,
+    /// The build for older CPUs without AVX2
+    Baseline,
+}
+
+/// Set from `--variant`, which takes precedence over CPU detection.
+static VARIANT_OVERRIDE: OnceLock<Variant> = OnceLock::new();
+
+pub fn set_variant(variant: Variant) {
+    let _ = VARIANT_OVERRIDE.set(variant);
+}
+
+pub fn get_variant() -> Variant {
+    if let Some(variant) = VARIANT_OVERRIDE.get() {
+        return *variant;
+    }
+
+    if supports_avx2() {
+        Variant::Default
+    } else {
+        Variant::Baseline
+    }
+}
+
+/// The default build of Bun crashes with "Illegal instruction" without AVX2.
+fn supports_avx2() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::arch::is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        true
+    }
+}
+
+/// The C library Bun is linked against. Glibc builds don't run on musl
+/// systems like Alpine, and the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    Glibc,
+    Musl,
+}
+
+pub fn get_libc() -> Libc {
+    static LIBC: OnceLock<Libc> = OnceLock::new();
+
+    *LIBC.get_or_init(|| {
+        if OS != "linux" {
+            return Libc::Glibc;
+        }
+        libc_of_binary(Path::new("/bin/sh"))
+            .or_else(libc_from_ldd)
+            .unwrap_or(Libc::Glibc)
+    })
+}
+
+/// Tells the libc of the system from the dynamic loader its binaries use,
+/// e.g. `/lib/ld-musl-x86_64.so.1` on Alpine.
+fn libc_of_binary(path: &Path) -> Option<Libc> {
+    use std::io::Read;
+
+    // The interpreter path sits at the start of the binary
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(64 * 1024)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    let interpreter = elf_interpreter(&head)?;
+    Some(if interpreter.contains("musl") {
+        Libc::Musl
+    } else {
+        Libc::Glibc
+    })
+}
+
+/// The `PT_INTERP` entry of an ELF binary, or `None` when it's not an ELF
+/// binary or it's statically linked.
+fn elf_interpreter(elf: &[u8]) -> Option<String> {
+    const PT_INTERP: u32 = 3;
+
+    if elf.get(..4)? != b"\x7fELF" {
+        return None;
+    }
+    let is_64_bit = *elf.get(4)? == 2;
+    let is_little_endian = *elf.get(5)? == 1;
+
+    let read = |offset: usize, size: usize| -> Option<u64> {
+        let bytes = elf.get(offset..offset + size)?;
+        let mut value = 0u64;
+        for i in 0..size {
+            let byte = if is_little_endian {
+                bytes[size - 1 - i]
+            } else {
+                bytes[i]
+            };
+            value = (value << 8) | byte as u64;
+        }
+        Some(value)
+    };
+
+    let (phoff, phentsize, phnum) = if is_64_bit {
+        (read(0x20, 8)?, read(0x36, 2)?, read(0x38, 2)?)
+    } else {
+        (read(0x1c, 4)?, read(0x2a, 2)?, read(0x2c, 2)?)
+    };
+
+    for i in 0..phnum {
+        let header = phoff.saturating_add(i * phentsize) as usize;
+        if read(header, 4)? as u32 != PT_INTERP {
+            continue;
+        }
+
+        let (offset, size) = if is_64_bit {
+            (read(header + 0x08, 8)?, read(header + 0x20, 8)?)
+        } else {
+            (read(header + 0x04, 4)?, read(header + 0x10, 4)?)
+        };
+        let interpreter = elf.get(offset as usize..offset.saturating_add(size) as usize)?;
+        let interpreter = interpreter.strip_suffix(&[0]).unwrap_or(interpreter);
+        return String::from_utf8(interpreter.to_vec()).ok();
+    }
+
+    None
+}
+
+/// Falls back to `ldd --version`, which names musl on musl systems (on
+/// stderr, with a non-zero exit code).
+fn libc_from_ldd() -> Option<Libc> {
+    let output = std::process::Command::new("ldd")
+        .arg("--version")
+        .output()
+        .ok()?;
+
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(if text.to_lowercase().contains("musl") {
+        Libc::Musl
+    } else {
+        Libc::Glibc
+    })
+}
 
 pub fn get_architecture() -> String {
-    let os = if OS == "macos" { "darwin" } else { OS };
+    architecture_for(OS, ARCH, get_libc(), get_variant())
+}
+
+fn architecture_for(os: &str, arch: &str, libc: Libc, variant: Variant) -> String {
+    let os = if os == "macos" { "darwin" } else { os };
 
-    let arch = match ARCH {
+    let arch = match arch {
         "x86_64" => "x64",
         "arm" => "aarch64",
-        _ => ARCH,
+        _ => arch,
     };
 
-    format!("{os}-{arch}")
+    let mut architecture = format!("{os}-{arch}");
+    if libc == Libc::Musl {
+        architecture.push_str("-musl");
+    }
+    // Baseline builds are only published for x64
+    if variant == Variant::Baseline && arch == "x64" {
+        architecture.push_str("-baseline");
+    }
+
+    architecture
+}
+
+/// What sets the build of this system apart from the regular one, e.g.
+/// `musl` or `musl-baseline`. Builds other than the regular one are installed
+/// in a directory of that name inside the version directory, so that for
+/// example a musl and a glibc build of the same version can coexist.
+pub fn get_build() -> Option<String> {
+    build_of_asset(&format!("bun-{}.zip", get_architecture()))
+}
+
+/// The build of a release asset like `bun-linux-x64-musl.zip`, `None` for
+/// the regular build.
+pub fn build_of_asset(asset_name: &str) -> Option<String> {
+    let architecture = asset_name.strip_prefix("bun-")?.strip_suffix(".zip")?;
+    let build = architecture.splitn(3, '-').nth(2)?;
+    (!build.is_empty()).then(|| build.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_architecture_for_variant() {
+        assert_eq!(
+            architecture_for("linux", "x86_64", Libc::Glibc, Variant::Default),
+            "linux-x64"
+        );
+        assert_eq!(
+            architecture_for("macos", "x86_64", Libc::Glibc, Variant::Baseline),
+            "darwin-x64-baseline"
+        );
+        assert_eq!(
+            architecture_for("linux", "aarch64", Libc::Glibc, Variant::Baseline),
+            "linux-aarch64"
+        );
+        assert_eq!(
+            architecture_for("linux", "x86_64", Libc::Musl, Variant::Baseline),
+            "linux-x64-musl-baseline"
+        );
+        assert_eq!(
+            architecture_for("linux", "aarch64", Libc::Musl, Variant::Default),
+            "linux-aarch64-musl"
+        );
+    }
+
+    #[test]
+    fn test_build_of_asset() {
+        assert_eq!(build_of_asset("bun-linux-x64.zip"), None);
+        assert_eq!(
+            build_of_asset("bun-linux-x64-musl.zip").as_deref(),
+            Some("musl")
+        );
+        assert_eq!(
+            build_of_asset("bun-linux-x64-musl-baseline.zip").as_deref(),
+            Some("musl-baseline")
+        );
+        assert_eq!(build_of_asset("my-bun.zip"), None);
+    }
+
+    /// A 64-bit little-endian ELF header with one `PT_INTERP` entry.
+    fn elf_with_interpreter(interpreter: &str) -> Vec<u8> {
+        let mut elf = vec![0u8; 0x78];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        elf[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+
+        let interpreter = format!("{interpreter}\0");
+        elf[0x40..0x44].copy_from_slice(&3u32.to_le_bytes());
+        let interpreter_offset = elf.len() as u64;
+        elf[0x48..0x50].copy_from_slice(&interpreter_offset.to_le_bytes());
+        elf[0x60..0x68].copy_from_slice(&(interpreter.len() as u64).to_le_bytes());
+        elf.extend_from_slice(interpreter.as_bytes());
+        elf
+    }
+
+    #[test]
+    fn test_elf_interpreter() {
+        assert_eq!(
+            elf_interpreter(&elf_with_interpreter("/lib/ld-musl-x86_64.so.1")).as_deref(),
+            Some("/lib/ld-musl-x86_64.so.1")
+        );
+        assert_eq!(
+            elf_interpreter(&elf_with_interpreter("/lib64/ld-linux-x86-64.so.2")).as_deref(),
+            Some("/lib64/ld-linux-x86-64.so.2")
+        );
+        assert_eq!(elf_interpreter(b"#!/bin/sh\n"), None);
+    }
 }
@@ -0,0 +1,164 @@
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+
+/// Without a terminal, a plain line is logged every time another
+/// `LOG_STEP_PERCENT` of the download is done.
+const LOG_STEP_PERCENT: u64 = 10;
+/// Log step when the server doesn't say how big the download is.
+const LOG_STEP_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Reports download progress on stderr, so it never mixes with the output of
+/// a command: a progress bar with bytes, speed and ETA on a terminal, plain
+/// log lines otherwise (CI logs, pipes).
+pub struct DownloadProgress {
+    bar: Option<ProgressBar>,
+    total: Option<u64>,
+    downloaded: u64,
+    next_log_at: u64,
+}
+
+impl DownloadProgress {
+    pub fn new(total: Option<u64>) -> Self {
+        Self::with_bar(total, std::io::stderr().is_terminal())
+    }
+
+    fn with_bar(total: Option<u64>, show_bar: bool) -> Self {
+        let bar = show_bar.then(|| {
+            // Without a total there's nothing to fill a bar or estimate with
+            let (bar, template) = match total {
+                Some(total) => (
+                    ProgressBar::new(total),
+                    "{bar:30} {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta})",
+                ),
+                None => (
+                    ProgressBar::new_spinner(),
+                    "{spinner} {bytes} ({bytes_per_sec})",
+                ),
+            };
+            bar.set_draw_target(ProgressDrawTarget::stderr());
+            bar.set_style(
+                ProgressStyle::with_template(template)
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            bar
+        });
+
+        let mut progress = DownloadProgress {
+            bar,
+            total,
+            downloaded: 0,
+            next_log_at: 0,
+        };
+        progress.next_log_at = progress.log_step();
+        progress
+    }
+
+    /// Starts from `bytes` already on disk, when a download is resumed.
+    pub fn resume_from(&mut self, bytes: u64) {
+        self.downloaded = bytes;
+        if let Some(bar) = &self.bar {
+            bar.set_position(bytes);
+        }
+        while self.next_log_at <= self.downloaded {
+            self.next_log_at += self.log_step();
+        }
+    }
+
+    fn log_step(&self) -> u64 {
+        match self.total {
+            Some(total) => (total * LOG_STEP_PERCENT / 100).max(1),
+            None => LOG_STEP_BYTES,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: usize) {
+        if let Some(line) = self.record(bytes) {
+            eprintln!("{line}");
+        }
+    }
+
+    /// Counts `bytes` more and returns the log line that's due, if any.
+    fn record(&mut self, bytes: usize) -> Option<String> {
+        self.downloaded += bytes as u64;
+
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.downloaded);
+            return None;
+        }
+
+        // The last step is left to `finish`
+        if self.downloaded < self.next_log_at || Some(self.downloaded) == self.total {
+            return None;
+        }
+
+        while self.next_log_at <= self.downloaded {
+            self.next_log_at += self.log_step();
+        }
+        Some(match self.total {
+            Some(total) => format!(
+                "Downloaded {} of {} ({}%)",
+                HumanBytes(self.downloaded),
+                HumanBytes(total),
+                self.downloaded * 100 / total.max(1)
+            ),
+            None => format!("Downloaded {}", HumanBytes(self.downloaded)),
+        })
+    }
+
+    pub fn finish(&self) {
+        match &self.bar {
+            Some(bar) => bar.finish_and_clear(),
+            None => eprintln!("Download complete ({})", HumanBytes(self.downloaded)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_lines(progress: &mut DownloadProgress, chunks: &[usize]) -> Vec<String> {
+        chunks
+            .iter()
+            .filter_map(|&chunk| progress.record(chunk))
+            .collect()
+    }
+
+    #[test]
+    fn test_logs_every_ten_percent() {
+        let mut progress = DownloadProgress::with_bar(Some(1000), false);
+
+        // A chunk crossing several steps logs once, and 100% is left to finish
+        let lines = log_lines(&mut progress, &[50, 60, 290, 100, 500]);
+
+        assert_eq!(
+            lines,
+            [
+                "Downloaded 110 B of 1000 B (11%)",
+                "Downloaded 400 B of 1000 B (40%)",
+                "Downloaded 500 B of 1000 B (50%)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logs_every_ten_megabytes_without_total() {
+        let mut progress = DownloadProgress::with_bar(None, false);
+        let mb = 1024 * 1024;
+
+        let lines = log_lines(&mut progress, &[6 * mb, 6 * mb, 6 * mb, 6 * mb]);
+
+        assert_eq!(lines, ["Downloaded 12.00 MiB", "Downloaded 24.00 MiB"]);
+    }
+
+    #[test]
+    fn test_resumed_download_logs_from_where_it_was() {
+        let mut progress = DownloadProgress::with_bar(Some(1000), false);
+        progress.resume_from(450);
+
+        let lines = log_lines(&mut progress, &[40, 20]);
+
+        assert_eq!(lines, ["Downloaded 510 B of 1000 B (51%)"]);
+    }
+}
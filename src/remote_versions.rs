@@ -0,0 +1,119 @@
+use crate::bun::Release;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The version of the cache file's layout. Bump it whenever `Release` gains
+/// fields, so lists cached without them are fetched again instead of being
+/// renewed by every 304.
+pub const CACHE_FORMAT: u32 = 1;
+
+/// The release list last fetched from the GitHub API, kept so that listing
+/// remote versions or resolving a range doesn't call the API every time.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedReleases {
+    /// `CACHE_FORMAT` when it was written, missing from the first caches
+    #[serde(default)]
+    pub format: u32,
+    /// The API the list came from, a list from another API isn't used
+    pub api_url: String,
+    /// When the list was fetched or last confirmed unchanged, in seconds
+    /// since the Unix epoch
+    pub fetched_at: u64,
+    /// The `ETag` of the first page, sent back as `If-None-Match`
+    pub etag: Option<String>,
+    pub releases: Vec<Release>,
+}
+
+impl CachedReleases {
+    /// The list cached at `path` for `api_url`. A missing or unreadable cache,
+    /// or one in another format, is just a cache miss.
+    pub fn read(path: &Path, api_url: &str) -> Option<CachedReleases> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        let cached: CachedReleases = serde_json::from_str(&contents).ok()?;
+        (cached.format == CACHE_FORMAT && cached.api_url == api_url).then_some(cached)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write next to the final path first, so a cut-off write is never read
+        let partial_path = path.with_extension("json.tmp");
+        std::fs::write(&partial_path, serde_json::to_string(self)?)?;
+        std::fs::rename(&partial_path, path)?;
+
+        Ok(())
+    }
+
+    /// Whether the list is younger than `ttl`, so the API needn't be asked.
+    pub fn is_fresh(&self, ttl: Duration, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) < ttl.as_secs()
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cached(fetched_at: u64) -> CachedReleases {
+        CachedReleases {
+            format: CACHE_FORMAT,
+            api_url: "https://api.github.com".to_string(),
+            fetched_at,
+            etag: Some("\"abc\"".to_string()),
+            releases: vec![Release {
+                version: "1.1.20".parse().unwrap(),
+                published_at: Some("2024-07-13T01:02:03Z".to_string()),
+                assets: vec!["bun-linux-x64.zip".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_cached_releases_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("remote-versions.json");
+
+        assert!(CachedReleases::read(&path, "https://api.github.com").is_none());
+        cached(100).write(&path).unwrap();
+
+        let read = CachedReleases::read(&path, "https://api.github.com").unwrap();
+        assert_eq!(read.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(read.releases[0].version.to_string(), "1.1.20");
+        assert!(CachedReleases::read(&path, "https://proxy.internal/github-api").is_none());
+    }
+
+    #[test]
+    fn test_cache_of_another_format_is_a_miss() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("remote-versions.json");
+        // As written before releases had dates and assets
+        std::fs::write(
+            &path,
+            r#"{"api_url":"https://api.github.com","fetched_at":100,"etag":"\"abc\"","releases":[{"version":"1.1.20"}]}"#,
+        )
+        .unwrap();
+
+        assert!(CachedReleases::read(&path, "https://api.github.com").is_none());
+    }
+
+    #[test]
+    fn test_cached_releases_expire_after_ttl() {
+        let ttl = Duration::from_secs(60 * 60);
+
+        assert!(cached(1_000).is_fresh(ttl, 1_000 + 59 * 60));
+        assert!(!cached(1_000).is_fresh(ttl, 1_000 + 60 * 60));
+        assert!(!cached(1_000).is_fresh(Duration::ZERO, 1_000));
+    }
+}
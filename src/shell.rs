@@ -0,0 +1,132 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Shells `bum env` can print setup code for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Guesses the user's shell from `$SHELL`.
+    pub fn detect() -> Option<Shell> {
+        let shell = std::env::var("SHELL").ok()?;
+        let name = Path::new(&shell).file_name()?.to_str()?;
+
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    pub fn prepend_path(&self, dir: &Path) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export PATH=\"{}:$PATH\"", dir.display()),
+            Shell::Fish => format!("set -gx PATH \"{}\" $PATH", dir.display()),
+        }
+    }
+
+    pub fn set_env(&self, name: &str, value: &Path) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}=\"{}\"", name, value.display()),
+            Shell::Fish => format!("set -gx {} \"{}\"", name, value.display()),
+        }
+    }
+
+    /// Code that runs `bum use --on-cd` whenever the working directory
+    /// changes, and once when the shell starts.
+    pub fn use_on_cd_hook(&self) -> &'static str {
+        match self {
+            Shell::Bash => {
+                r#"__bum_use_on_cd() {
+  if [ "${__BUM_LAST_PWD:-}" != "$PWD" ]; then
+    __BUM_LAST_PWD="$PWD"
+    bum use --on-cd
+  fi
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";__bum_use_on_cd;"* ]]; then
+  PROMPT_COMMAND="__bum_use_on_cd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi"#
+            }
+            Shell::Zsh => {
+                r#"autoload -U add-zsh-hook
+_bum_use_on_cd() {
+  bum use --on-cd
+}
+add-zsh-hook chpwd _bum_use_on_cd
+_bum_use_on_cd"#
+            }
+            Shell::Fish => {
+                r#"function _bum_use_on_cd --on-variable PWD --description 'Switch Bun version on cd'
+  status --is-command-substitution; and return
+  bum use --on-cd
+end
+_bum_use_on_cd"#
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_hook_runs_once_per_directory_change() {
+        let hook = Shell::Bash.use_on_cd_hook();
+
+        assert!(hook.contains(r#"if [ "${__BUM_LAST_PWD:-}" != "$PWD" ]; then"#));
+        assert!(hook.contains("    bum use --on-cd\n"));
+        // Sourcing the setup twice must not add the hook twice
+        assert!(hook.contains(r#"if [[ ";${PROMPT_COMMAND:-};" != *";__bum_use_on_cd;"* ]]; then"#));
+        assert!(
+            hook.contains(r#"PROMPT_COMMAND="__bum_use_on_cd${PROMPT_COMMAND:+;$PROMPT_COMMAND}""#)
+        );
+    }
+
+    #[test]
+    fn test_zsh_hook_runs_on_chpwd_and_at_startup() {
+        let hook = Shell::Zsh.use_on_cd_hook();
+
+        assert!(hook.starts_with("autoload -U add-zsh-hook\n"));
+        assert!(hook.contains("_bum_use_on_cd() {\n  bum use --on-cd\n}"));
+        assert!(hook.contains("add-zsh-hook chpwd _bum_use_on_cd\n"));
+        assert!(hook.ends_with("\n_bum_use_on_cd"));
+    }
+
+    #[test]
+    fn test_fish_hook_runs_on_pwd_change_and_at_startup() {
+        let hook = Shell::Fish.use_on_cd_hook();
+
+        assert!(hook.starts_with("function _bum_use_on_cd --on-variable PWD"));
+        assert!(hook.contains("status --is-command-substitution; and return\n"));
+        assert!(hook.contains("  bum use --on-cd\nend"));
+        assert!(hook.ends_with("\n_bum_use_on_cd"));
+    }
+
+    #[test]
+    fn test_env_lines_per_shell() {
+        let dir = Path::new("/home/me/.bum/sessions/1/bin");
+
+        assert_eq!(
+            Shell::Bash.prepend_path(dir),
+            r#"export PATH="/home/me/.bum/sessions/1/bin:$PATH""#
+        );
+        assert_eq!(
+            Shell::Fish.prepend_path(dir),
+            r#"set -gx PATH "/home/me/.bum/sessions/1/bin" $PATH"#
+        );
+        assert_eq!(
+            Shell::Zsh.set_env("BUN_INSTALL", dir),
+            r#"export BUN_INSTALL="/home/me/.bum/sessions/1/bin""#
+        );
+        assert_eq!(
+            Shell::Fish.set_env("BUN_INSTALL", dir),
+            r#"set -gx BUN_INSTALL "/home/me/.bum/sessions/1/bin""#
+        );
+    }
+}
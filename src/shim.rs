@@ -0,0 +1,264 @@
+use crate::bun;
+use crate::bun::BUN_BIN_NAME;
+use crate::commands::{
+    pinned_version_in, read_default_from, set_default, version_dir_in, PinnedVersion, BUN_BIN_PATH,
+    DEFAULT_VERSION_PATH, FOLDER_VERSION_BASE,
+};
+use anyhow::{bail, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Names bum answers to as the shim. `bunx` is a link to `bun` in a regular
+/// Bun install, and Bun tells the two apart by the name it was run as.
+const SHIM_NAMES: [&str; 2] = ["bun", "bunx"];
+
+/// Returns the name bum was run as when it's running as the `bun` shim.
+pub fn invoked_as() -> Option<String> {
+    let arg0 = std::env::args_os().next()?;
+    let name = Path::new(&arg0).file_stem()?.to_str()?;
+
+    SHIM_NAMES.contains(&name).then(|| name.to_string())
+}
+
+/// Runs the Bun version pinned for the current directory, or the default one.
+///
+/// This is on the path of every `bun` invocation, so it stays synchronous,
+/// never touches the network and only looks at installed versions.
+pub fn run(invoked_as: &str) -> ! {
+    let bun_path = match resolve_bun_path() {
+        Ok(bun_path) => bun_path,
+        Err(e) => {
+            eprintln!("bum: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let mut child = std::process::Command::new(&bun_path);
+    child.args(args);
+
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let error = child.arg0(invoked_as).exec();
+        eprintln!("bum: Failed to run {}: {}", bun_path.display(), error);
+        std::process::exit(1);
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = invoked_as;
+        match child.status() {
+            Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+            Err(error) => {
+                eprintln!("bum: Failed to run {}: {}", bun_path.display(), error);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn resolve_bun_path() -> Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    resolve_bun_path_in(&current_dir, &FOLDER_VERSION_BASE, &DEFAULT_VERSION_PATH)
+}
+
+fn resolve_bun_path_in(dir: &Path, versions_dir: &Path, default_path: &Path) -> Result<PathBuf> {
+    match pinned_version_in(dir, versions_dir, default_path)? {
+        Some(PinnedVersion::Installed { version, .. }) => {
+            Ok(version_dir_in(versions_dir, &version).join(BUN_BIN_NAME))
+        }
+        Some(PinnedVersion::Missing { version, source }) => bail!(
+            "Bun {} from {} is not installed, please use bum use {} to install it",
+            version,
+            source,
+            version
+        ),
+        None => bail!(
+            "No Bun version is pinned here and no default is set, please use bum default <version>"
+        ),
+    }
+}
+
+/// Whether `BUN_BIN_PATH` currently is the shim rather than a copy of Bun.
+pub fn is_installed() -> bool {
+    is_shim_at(&BUN_BIN_PATH)
+}
+
+fn is_shim_at(bun_bin_path: &Path) -> bool {
+    let Ok(target) = std::fs::read_link(bun_bin_path) else {
+        return false;
+    };
+
+    target.file_stem().and_then(|stem| stem.to_str()) == Some("bum")
+}
+
+fn current_bum_exe() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+
+    // Through npm, bum runs inside node and there's no binary to link to
+    if exe.file_stem().and_then(|stem| stem.to_str()) != Some("bum") {
+        bail!("The bun shim needs the standalone bum binary, please install it with install.sh");
+    }
+
+    Ok(exe)
+}
+
+/// Replaces the active `bun` with the shim. The active version becomes the
+/// default unless one is already set, so `bun` keeps working everywhere.
+pub async fn install() -> Result<()> {
+    if cfg!(windows) {
+        bail!("The bun shim is only supported on macOS and Linux");
+    }
+
+    let exe = current_bum_exe()?;
+
+    if read_default_from(&DEFAULT_VERSION_PATH)?.is_none() && !is_installed() {
+        let active_version = bun::get_active_version().await;
+        if !active_version.is_empty() {
+            set_default(&active_version).await?;
+        }
+    }
+
+    install_to(&exe, &BUN_BIN_PATH)?;
+    println!("The bun shim is installed at {}", BUN_BIN_PATH.display());
+
+    Ok(())
+}
+
+fn install_to(exe: &Path, bun_bin_path: &Path) -> Result<()> {
+    if let Some(parent) = bun_bin_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if std::fs::symlink_metadata(bun_bin_path).is_ok() {
+        std::fs::remove_file(bun_bin_path)?;
+    }
+
+    #[cfg(not(windows))]
+    std::os::unix::fs::symlink(exe, bun_bin_path)?;
+
+    #[cfg(windows)]
+    let _ = exe;
+
+    Ok(())
+}
+
+/// Removes the shim. Activate a version again with `bum use` afterwards.
+pub fn uninstall() -> Result<()> {
+    if !is_installed() {
+        bail!("The bun shim is not installed");
+    }
+
+    std::fs::remove_file(BUN_BIN_PATH.as_ref())?;
+    println!("The bun shim has been removed, please use bum use <version> to activate a version");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_install_shim_replaces_copied_bun() {
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join(".bum").join("bin").join("bum");
+        let bun_bin_path = temp_dir.path().join(".bun").join("bin").join("bun");
+
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::write(&exe, "bum").unwrap();
+        std::fs::create_dir_all(bun_bin_path.parent().unwrap()).unwrap();
+        std::fs::write(&bun_bin_path, "copied bun").unwrap();
+        assert!(!is_shim_at(&bun_bin_path));
+
+        install_to(&exe, &bun_bin_path).unwrap();
+
+        assert!(is_shim_at(&bun_bin_path));
+        assert_eq!(std::fs::read_to_string(&bun_bin_path).unwrap(), "bum");
+    }
+
+    struct ShimEnv {
+        _temp_dir: TempDir,
+        project: PathBuf,
+        versions_dir: PathBuf,
+        default_path: PathBuf,
+    }
+
+    impl ShimEnv {
+        fn new(installed: &[&str]) -> Self {
+            let temp_dir = TempDir::new().unwrap();
+            let project = temp_dir.path().join("project");
+            let versions_dir = temp_dir.path().join(".bum").join("bun-versions");
+            std::fs::create_dir_all(project.join(".git")).unwrap();
+
+            for version in installed {
+                let version_dir = version_dir_in(&versions_dir, &version.parse().unwrap());
+                std::fs::create_dir_all(&version_dir).unwrap();
+                std::fs::write(version_dir.join(BUN_BIN_NAME), "mock bun").unwrap();
+            }
+
+            ShimEnv {
+                default_path: temp_dir.path().join(".bum").join("default"),
+                _temp_dir: temp_dir,
+                project,
+                versions_dir,
+            }
+        }
+
+        fn resolve(&self) -> Result<PathBuf> {
+            resolve_bun_path_in(&self.project, &self.versions_dir, &self.default_path)
+        }
+
+        fn bun_of(&self, version: &str) -> PathBuf {
+            version_dir_in(&self.versions_dir, &version.parse().unwrap()).join(BUN_BIN_NAME)
+        }
+    }
+
+    #[test]
+    fn test_shim_runs_pinned_version() {
+        let env = ShimEnv::new(&["1.1.18", "1.1.20", "1.2.0"]);
+        std::fs::write(&env.default_path, "1.2.0").unwrap();
+        std::fs::write(
+            env.project.join("package.json"),
+            r#"{ "engines": { "bun": "~1.1.0" } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(env.resolve().unwrap(), env.bun_of("1.1.20"));
+
+        std::fs::write(env.project.join(".bumrc"), "1.1.18").unwrap();
+        assert_eq!(env.resolve().unwrap(), env.bun_of("1.1.18"));
+    }
+
+    #[test]
+    fn test_shim_falls_back_to_default_version() {
+        let env = ShimEnv::new(&["1.1.20"]);
+
+        let error = env.resolve().unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("No Bun version is pinned here"));
+
+        std::fs::write(&env.default_path, "1.1").unwrap();
+        assert_eq!(env.resolve().unwrap(), env.bun_of("1.1.20"));
+    }
+
+    #[test]
+    fn test_shim_fails_for_missing_install() {
+        let env = ShimEnv::new(&["1.1.20"]);
+        std::fs::write(env.project.join(".bun-version"), "1.0.36").unwrap();
+
+        let error = env.resolve().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Bun 1.0.36 from {} is not installed, please use bum use 1.0.36 to install it",
+                env.project.join(".bun-version").display()
+            )
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+/// Serves one canned response per connection, for tests that talk HTTP.
+/// Returns the base URL, which replaces `{base}` in the responses, and a
+/// handle that yields the (lowercased) requests received.
+pub fn serve(responses: &[&str]) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let responses: Vec<String> = responses
+        .iter()
+        .map(|response| response.replace("{base}", &base))
+        .collect();
+
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            requests.push(String::from_utf8(request).unwrap().to_lowercase());
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+
+    (base, server)
+}
+
+/// A complete response with `headers` and `body`.
+pub fn response(status: &str, headers: &[&str], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {status}\r\n");
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    response
+}
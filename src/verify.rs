@@ -0,0 +1,248 @@
+use anyhow::{anyhow, bail, Result};
+use pgp::composed::{CleartextSignedMessage, Deserializable, DetachedSignature, SignedPublicKey};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+/// Name of the checksum file published with every Bun release.
+pub const CHECKSUMS_FILE_NAME: &str = "SHASUMS256.txt";
+/// Name of the PGP signature of the checksum file.
+pub const SIGNATURE_FILE_NAME: &str = "SHASUMS256.txt.asc";
+
+/// Armored public keys trusted to sign Bun releases, built into bum.
+// TODO: Add Bun's release signing key (the key behind SHASUMS256.txt.asc,
+// published on bun.sh) here, e.g. include_str!("../keys/bun-release.asc").
+// Until then, installs fail unless a key is trusted in ~/.bum/trusted-keys
+// or through BUM_TRUSTED_KEY.
+pub const EMBEDDED_RELEASE_KEYS: &[&str] = &[];
+
+/// Loads the keys trusted to sign Bun releases: `embedded_keys` (normally
+/// `EMBEDDED_RELEASE_KEYS`), the armored keys in `trusted_keys_dir` (e.g. the
+/// signing key of an internal mirror), and the key file at `extra_key_path`
+/// if there is one.
+pub fn load_trusted_keys(
+    embedded_keys: &[&str],
+    trusted_keys_dir: &Path,
+    extra_key_path: Option<&Path>,
+) -> Result<Vec<SignedPublicKey>> {
+    let mut armored_keys: Vec<(String, String)> = embedded_keys
+        .iter()
+        .map(|key| ("built-in release key".to_string(), key.to_string()))
+        .collect();
+
+    if trusted_keys_dir.is_dir() {
+        for entry in std::fs::read_dir(trusted_keys_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("asc") {
+                armored_keys.push((path.display().to_string(), std::fs::read_to_string(&path)?));
+            }
+        }
+    }
+
+    if let Some(path) = extra_key_path {
+        armored_keys.push((path.display().to_string(), std::fs::read_to_string(path)?));
+    }
+
+    armored_keys
+        .iter()
+        .map(|(source, armored)| {
+            let (key, _) = SignedPublicKey::from_string(armored)
+                .map_err(|e| anyhow!("Invalid public key in {}: {}", source, e))?;
+            key.verify_bindings()
+                .map_err(|e| anyhow!("Invalid public key in {}: {}", source, e))?;
+            Ok(key)
+        })
+        .collect()
+}
+
+/// Checks the signature of a release's checksum file against `keys` and
+/// returns the checksums that can be trusted. Bun clearsigns the checksums,
+/// in which case the signed text is used; a detached signature over
+/// `checksums` is accepted too.
+pub fn verify_checksums_signature(
+    checksums: &str,
+    signature: &str,
+    keys: &[SignedPublicKey],
+) -> Result<String> {
+    if signature
+        .trim_start()
+        .starts_with("-----BEGIN PGP SIGNED MESSAGE-----")
+    {
+        let (message, _) = CleartextSignedMessage::from_string(signature)
+            .map_err(|e| anyhow!("Invalid {}: {}", SIGNATURE_FILE_NAME, e))?;
+        let signed = keys.iter().any(|key| {
+            message.verify(&key.primary_key).is_ok()
+                || key
+                    .public_subkeys
+                    .iter()
+                    .any(|subkey| message.verify(&subkey.key).is_ok())
+        });
+        if !signed {
+            bail!("{} is not signed by a trusted key", SIGNATURE_FILE_NAME);
+        }
+        return Ok(message.signed_text());
+    }
+
+    let (detached, _) = DetachedSignature::from_string(signature)
+        .map_err(|e| anyhow!("Invalid {}: {}", SIGNATURE_FILE_NAME, e))?;
+    let signed = keys.iter().any(|key| {
+        detached
+            .verify(&key.primary_key, checksums.as_bytes())
+            .is_ok()
+            || key
+                .public_subkeys
+                .iter()
+                .any(|subkey| detached.verify(&subkey.key, checksums.as_bytes()).is_ok())
+    });
+    if !signed {
+        bail!("{} is not signed by a trusted key", SIGNATURE_FILE_NAME);
+    }
+
+    Ok(checksums.to_string())
+}
+
+/// Finds the SHA-256 of `asset_name` in a `SHASUMS256.txt`, which has one
+/// `<hex digest>  <file name>` line per release asset.
+pub fn expected_checksum(checksums: &str, asset_name: &str) -> Result<String> {
+    checksums
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let digest = fields.next()?;
+            // `sha256sum -b` marks file names with a leading `*`
+            let file_name = fields.next()?.trim_start_matches('*');
+            (file_name == asset_name).then(|| digest.to_ascii_lowercase())
+        })
+        .ok_or_else(|| anyhow!("{} has no checksum for {}", CHECKSUMS_FILE_NAME, asset_name))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Checks `path` against its entry in `checksums`, so a corrupted or
+/// tampered download never gets extracted.
+pub fn verify_checksum(path: &Path, checksums: &str, asset_name: &str) -> Result<()> {
+    let expected = expected_checksum(checksums, asset_name)?;
+    let actual = sha256_file(path)?;
+
+    if expected != actual {
+        bail!(
+            "Checksum mismatch for {}: expected {}, got {}. The download may be corrupted or tampered with, refusing to install it",
+            asset_name,
+            expected,
+            actual
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // sha256("mock bun zip")
+    const MOCK_ZIP_SHA256: &str =
+        "0b3e44c0d506ab20e54574806b9bd2a4833dfc4621aa6e9f1fc56c31443d9d65";
+
+    fn checksums_with(digest: &str) -> String {
+        format!(
+            "0000000000000000000000000000000000000000000000000000000000000000  bun-darwin-aarch64.zip\n{digest}  bun-linux-x64.zip\n"
+        )
+    }
+
+    const TRUSTED_KEY: &str = include_str!("../fixtures/signing/trusted-key.asc");
+    const UNTRUSTED_KEY: &str = include_str!("../fixtures/signing/untrusted-key.asc");
+    const CHECKSUMS: &str = include_str!("../fixtures/signing/SHASUMS256.txt");
+    const CLEARSIGNED_CHECKSUMS: &str = include_str!("../fixtures/signing/SHASUMS256.txt.asc");
+    const DETACHED_SIGNATURE: &str = include_str!("../fixtures/signing/SHASUMS256.txt.sig");
+
+    fn keys_in(armored_keys: &[&str]) -> Vec<SignedPublicKey> {
+        let temp_dir = TempDir::new().unwrap();
+        for (i, key) in armored_keys.iter().enumerate() {
+            std::fs::write(temp_dir.path().join(format!("key-{i}.asc")), key).unwrap();
+        }
+        load_trusted_keys(&[], temp_dir.path(), None).unwrap()
+    }
+
+    #[test]
+    fn test_signature_from_trusted_key() {
+        let keys = keys_in(&[UNTRUSTED_KEY, TRUSTED_KEY]);
+
+        let checksums = verify_checksums_signature("", CLEARSIGNED_CHECKSUMS, &keys).unwrap();
+        assert_eq!(
+            expected_checksum(&checksums, "bun-linux-x64.zip").unwrap(),
+            MOCK_ZIP_SHA256
+        );
+
+        let checksums = verify_checksums_signature(CHECKSUMS, DETACHED_SIGNATURE, &keys).unwrap();
+        assert_eq!(checksums, CHECKSUMS);
+    }
+
+    #[test]
+    fn test_signature_from_untrusted_key_is_rejected() {
+        let keys = keys_in(&[UNTRUSTED_KEY]);
+
+        assert!(verify_checksums_signature("", CLEARSIGNED_CHECKSUMS, &keys).is_err());
+        assert!(verify_checksums_signature(CHECKSUMS, DETACHED_SIGNATURE, &keys).is_err());
+    }
+
+    #[test]
+    fn test_tampered_checksums_are_rejected() {
+        let keys = keys_in(&[TRUSTED_KEY]);
+
+        let tampered = CHECKSUMS.replace("0b3e44c0", "deadbeef");
+        assert!(verify_checksums_signature(&tampered, DETACHED_SIGNATURE, &keys).is_err());
+
+        let tampered = CLEARSIGNED_CHECKSUMS.replace("0b3e44c0", "deadbeef");
+        assert!(verify_checksums_signature("", &tampered, &keys).is_err());
+    }
+
+    #[test]
+    fn test_expected_checksum() {
+        let checksums = checksums_with(MOCK_ZIP_SHA256);
+
+        assert_eq!(
+            expected_checksum(&checksums, "bun-linux-x64.zip").unwrap(),
+            MOCK_ZIP_SHA256
+        );
+        assert!(expected_checksum(&checksums, "bun-linux-aarch64.zip").is_err());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("bun-linux-x64.zip");
+        std::fs::write(&zip_path, "mock bun zip").unwrap();
+        assert_eq!(sha256_file(&zip_path).unwrap(), MOCK_ZIP_SHA256);
+
+        assert!(verify_checksum(
+            &zip_path,
+            &checksums_with(MOCK_ZIP_SHA256),
+            "bun-linux-x64.zip"
+        )
+        .is_ok());
+
+        let error = verify_checksum(
+            &zip_path,
+            &checksums_with(&"f".repeat(64)),
+            "bun-linux-x64.zip",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Checksum mismatch"));
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{anyhow, bail, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An exact Bun version. Parsing accepts an optional leading `v` (and the
+/// `bun-v` prefix used by release tags), and ordering follows semver, so
+/// `1.10.0` sorts above `1.9.0` and `1.2.0-canary.1` below `1.2.0`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BunVersion(Version);
+
+impl BunVersion {
+    pub fn is_prerelease(&self) -> bool {
+        !self.0.pre.is_empty()
+    }
+
+    /// Whether both name the same release. Build metadata (`+...`) is left
+    /// out, as semver says it doesn't tell versions apart, though `==` on the
+    /// underlying `Version` does.
+    pub fn same_release(&self, other: &BunVersion) -> bool {
+        let release = |version: &BunVersion| {
+            let Version {
+                major,
+                minor,
+                patch,
+                ref pre,
+                ..
+            } = version.0;
+            (major, minor, patch, pre.clone())
+        };
+
+        release(self) == release(other)
+    }
+}
+
+impl FromStr for BunVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let trimmed = input.trim();
+        let unprefixed = trimmed.strip_prefix("bun-").unwrap_or(trimmed);
+        let unprefixed = unprefixed.strip_prefix('v').unwrap_or(unprefixed);
+
+        Version::parse(unprefixed).map(BunVersion).map_err(|_| {
+            anyhow!("Invalid Bun version \"{trimmed}\", expected something like 1.1.20")
+        })
+    }
+}
+
+impl TryFrom<String> for BunVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(input: String) -> Result<Self> {
+        input.parse()
+    }
+}
+
+impl From<BunVersion> for String {
+    fn from(version: BunVersion) -> String {
+        version.to_string()
+    }
+}
+
+impl fmt::Display for BunVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A version as asked for by the user, on the command line or in a `.bumrc`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionRequest {
+    /// The newest stable release.
+    Latest,
+    /// A single, fully specified version such as `1.1.20`.
+    Exact(BunVersion),
+    /// A partial version or a semver range such as `1.1`, `^1.1.0` or `~1.2`.
+    /// Alternatives separated by `||` are kept as separate requirements.
+    Range(Vec<VersionReq>),
+}
+
+impl VersionRequest {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+
+        if input.is_empty() {
+            bail!("Version can't be empty");
+        }
+
+        if input.eq_ignore_ascii_case("latest") {
+            return Ok(VersionRequest::Latest);
+        }
+
+        if let Ok(version) = input.parse::<BunVersion>() {
+            return Ok(VersionRequest::Exact(version));
+        }
+
+        let requirements = input
+            .split("||")
+            .map(parse_requirement)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(VersionRequest::Range(requirements))
+    }
+
+    pub fn matches(&self, version: &BunVersion) -> bool {
+        match self {
+            VersionRequest::Latest => !version.is_prerelease(),
+            VersionRequest::Exact(exact) => exact == version,
+            VersionRequest::Range(requirements) => {
+                requirements.iter().any(|r| r.matches(&version.0))
+            }
+        }
+    }
+
+    /// Returns the newest of `versions` that satisfies this request.
+    pub fn newest_match<'a, I>(&self, versions: I) -> Option<&'a BunVersion>
+    where
+        I: IntoIterator<Item = &'a BunVersion>,
+    {
+        versions.into_iter().filter(|v| self.matches(v)).max()
+    }
+}
+
+impl fmt::Display for VersionRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionRequest::Latest => write!(f, "latest"),
+            VersionRequest::Exact(version) => write!(f, "{version}"),
+            VersionRequest::Range(requirements) => {
+                let requirements: Vec<String> =
+                    requirements.iter().map(|r| r.to_string()).collect();
+                write!(f, "{}", requirements.join(" || "))
+            }
+        }
+    }
+}
+
+fn parse_requirement(input: &str) -> Result<VersionReq> {
+    // npm separates comparators with spaces (">=1.0.0 <2.0.0") while the semver
+    // crate expects commas, so glue operators to their version and join with ", ".
+    let mut comparators: Vec<String> = Vec::new();
+    let mut pending_operator = String::new();
+    for token in input.split(|c: char| c.is_whitespace() || c == ',') {
+        if token.is_empty() {
+            continue;
+        }
+        if token.chars().all(|c| "<>=~^".contains(c)) {
+            pending_operator.push_str(token);
+            continue;
+        }
+
+        let token = token.strip_prefix('v').unwrap_or(token);
+        let comparator =
+            if pending_operator.is_empty() && token.starts_with(|c: char| c.is_ascii_digit()) {
+                // A bare partial version like `1.1` means any 1.1.x release, the
+                // way nvm and npm read it, rather than semver's default of `^1.1`.
+                format!("={token}")
+            } else {
+                format!("{pending_operator}{token}")
+            };
+        pending_operator.clear();
+        comparators.push(comparator);
+    }
+
+    if comparators.is_empty() {
+        bail!("Invalid version or range: \"{}\"", input.trim());
+    }
+
+    VersionReq::parse(&comparators.join(", "))
+        .map_err(|e| anyhow!("Invalid version or range \"{}\": {e}", input.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(input: &str) -> BunVersion {
+        input.parse().unwrap()
+    }
+
+    fn versions(list: &[&str]) -> Vec<BunVersion> {
+        list.iter().map(|v| version(v)).collect()
+    }
+
+    #[test]
+    fn test_parse_bun_version() {
+        assert_eq!(version("v1.0.3").to_string(), "1.0.3");
+        assert_eq!(version("bun-v1.0.3").to_string(), "1.0.3");
+        assert_eq!(
+            version("1.1.0-canary.12+a1b2c3d").to_string(),
+            "1.1.0-canary.12+a1b2c3d"
+        );
+        assert_eq!(version("2.0.0-beta").to_string(), "2.0.0-beta");
+    }
+
+    #[test]
+    fn test_only_leading_v_is_stripped() {
+        assert!("vv1.0.0".parse::<BunVersion>().is_err());
+        assert!("1.0.0-dev".parse::<BunVersion>().is_ok());
+        assert_eq!(version("v1.0.0-dev").to_string(), "1.0.0-dev");
+    }
+
+    #[test]
+    fn test_reject_invalid_versions() {
+        for input in ["", "canary", "1.0", "1.0.0.0", "latest", "v"] {
+            assert!(
+                input.parse::<BunVersion>().is_err(),
+                "{input:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_semver_ordering() {
+        let mut list = versions(&[
+            "1.9.0",
+            "1.10.0",
+            "1.2.0-canary.2",
+            "1.2.0",
+            "1.2.0-canary.10",
+        ]);
+        list.sort();
+
+        assert_eq!(
+            list.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            vec![
+                "1.2.0-canary.2",
+                "1.2.0-canary.10",
+                "1.2.0",
+                "1.9.0",
+                "1.10.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_release_ignores_build_metadata() {
+        assert!(version("1.1.21-canary.1+6ee5ee5b2").same_release(&version("1.1.21-canary.1")));
+        assert!(version("1.1.20").same_release(&version("v1.1.20+linux")));
+        assert!(!version("1.1.21-canary.1").same_release(&version("1.1.21-canary.2")));
+        assert!(!version("1.1.21-canary.1").same_release(&version("1.1.21")));
+    }
+
+    #[test]
+    fn test_parse_exact_version() {
+        assert_eq!(
+            VersionRequest::parse("v1.0.3").unwrap(),
+            VersionRequest::Exact(version("1.0.3"))
+        );
+        assert_eq!(
+            VersionRequest::parse("1.0.3").unwrap(),
+            VersionRequest::Exact(version("1.0.3"))
+        );
+    }
+
+    #[test]
+    fn test_partial_version_matches_minor_line() {
+        let available = versions(&["1.0.36", "1.1.0", "1.1.20", "1.2.0"]);
+        let request = VersionRequest::parse("1.1").unwrap();
+
+        assert_eq!(request.newest_match(&available), Some(&version("1.1.20")));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let available = versions(&["1.0.36", "1.1.0", "1.1.20", "1.2.3", "2.0.0"]);
+
+        let newest = |input: &str| {
+            VersionRequest::parse(input)
+                .unwrap()
+                .newest_match(&available)
+                .map(|v| v.to_string())
+        };
+
+        assert_eq!(newest("^1.1.0").as_deref(), Some("1.2.3"));
+        assert_eq!(newest("~1.1").as_deref(), Some("1.1.20"));
+        assert_eq!(newest(">=1.0.0 <1.2.0").as_deref(), Some("1.1.20"));
+        assert_eq!(newest("1.0 || 1.1.0").as_deref(), Some("1.1.0"));
+        assert_eq!(newest("^3").as_deref(), None);
+    }
+
+    #[test]
+    fn test_latest_skips_prereleases() {
+        let available = versions(&["1.1.20", "1.2.0-canary.1"]);
+        let request = VersionRequest::parse("latest").unwrap();
+
+        assert_eq!(request.newest_match(&available), Some(&version("1.1.20")));
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        assert!(VersionRequest::parse("").is_err());
+        assert!(VersionRequest::parse("banana").is_err());
+    }
+}
@@ -0,0 +1,442 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The kinds of files a project can pin its Bun version in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionFileKind {
+    /// `.bumrc`, containing just the version.
+    Bumrc,
+    /// `.bun-version`, containing just the version.
+    BunVersion,
+    /// asdf's `.tool-versions`, with a line like `bun 1.1.20`.
+    ToolVersions,
+    /// `"packageManager": "bun@1.1.20"` in `package.json`.
+    PackageManager,
+    /// `"engines": { "bun": "^1.1.0" }` in `package.json`.
+    Engines,
+}
+
+/// Precedence of the version files within one directory, highest first.
+/// Directories closer to where bum runs always win over their parents.
+const LOOKUP_ORDER: [VersionFileKind; 5] = [
+    VersionFileKind::Bumrc,
+    VersionFileKind::BunVersion,
+    VersionFileKind::ToolVersions,
+    VersionFileKind::PackageManager,
+    VersionFileKind::Engines,
+];
+
+impl VersionFileKind {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            VersionFileKind::Bumrc => ".bumrc",
+            VersionFileKind::BunVersion => ".bun-version",
+            VersionFileKind::ToolVersions => ".tool-versions",
+            VersionFileKind::PackageManager | VersionFileKind::Engines => "package.json",
+        }
+    }
+
+    fn reads_package_json(&self) -> bool {
+        matches!(
+            self,
+            VersionFileKind::PackageManager | VersionFileKind::Engines
+        )
+    }
+
+    /// The version in a `.bumrc`, `.bun-version` or `.tool-versions`.
+    fn read_version(&self, content: &str) -> Option<String> {
+        let version = match self {
+            VersionFileKind::Bumrc | VersionFileKind::BunVersion => content.trim().to_string(),
+            VersionFileKind::ToolVersions => content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default())
+                .find_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    match fields.next() {
+                        Some("bun") => fields.next().map(str::to_string),
+                        _ => None,
+                    }
+                })?,
+            VersionFileKind::PackageManager | VersionFileKind::Engines => return None,
+        };
+
+        non_empty(&version)
+    }
+
+    /// The version in a parsed `package.json`.
+    fn read_package_version(&self, package: &Value) -> Option<String> {
+        let version = match self {
+            VersionFileKind::PackageManager => {
+                let package_manager = package.get("packageManager")?.as_str()?;
+                let version = package_manager.strip_prefix("bun@")?;
+                // Corepack may append the archive hash, e.g. `bun@1.1.20+sha512.abc`
+                version.split('+').next().unwrap_or_default()
+            }
+            VersionFileKind::Engines => package.get("engines")?.get("bun")?.as_str()?,
+            _ => return None,
+        };
+
+        non_empty(version)
+    }
+}
+
+fn non_empty(version: &str) -> Option<String> {
+    let version = version.trim();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_string())
+    }
+}
+
+/// A Bun version pinned by a project, and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionFile {
+    pub kind: VersionFileKind,
+    pub path: PathBuf,
+    pub version: String,
+}
+
+impl VersionFile {
+    /// Replaces the pinned version in this file, leaving the rest of it as is.
+    pub fn write_version(&self, version: &str) -> Result<()> {
+        let content = match self.kind {
+            VersionFileKind::Bumrc | VersionFileKind::BunVersion => format!("{version}\n"),
+            VersionFileKind::ToolVersions => {
+                let content = fs::read_to_string(&self.path)?;
+                let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+                let bun_line = lines
+                    .iter_mut()
+                    .find(|line| line.split_whitespace().next() == Some("bun"));
+                match bun_line {
+                    Some(line) => {
+                        let comment = line.find('#').map(|i| format!(" {}", &line[i..]));
+                        *line = format!("bun {version}{}", comment.unwrap_or_default());
+                    }
+                    None => lines.push(format!("bun {version}")),
+                }
+                format!("{}\n", lines.join("\n"))
+            }
+            VersionFileKind::PackageManager => {
+                let content = fs::read_to_string(&self.path)?;
+                // Edit the text rather than re-serializing, to keep the
+                // formatting and key order of package.json.
+                let Some(value) = top_level_string_value(&content, "packageManager") else {
+                    bail!("Failed to find packageManager in {}", self.path.display());
+                };
+                format!(
+                    "{}bun@{version}{}",
+                    &content[..value.start],
+                    &content[value.end..]
+                )
+            }
+            VersionFileKind::Engines => {
+                bail!(
+                    "engines.bun in {} lists compatible versions and can't be pinned",
+                    self.path.display()
+                );
+            }
+        };
+
+        fs::write(&self.path, content)?;
+
+        Ok(())
+    }
+}
+
+/// Where the string value of `key` in the top-level object of `json` is,
+/// without its quotes. Keys of nested objects and strings that merely
+/// contain `key` don't count.
+fn top_level_string_value(json: &str, key: &str) -> Option<Range<usize>> {
+    let bytes = json.as_bytes();
+    let skip_whitespace = |mut i: usize| {
+        while bytes.get(i).is_some_and(|byte| byte.is_ascii_whitespace()) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth -= 1,
+            b'"' => {
+                let end = string_end(bytes, i)?;
+                let after = skip_whitespace(end + 1);
+                let is_key = depth == 1 && bytes.get(after) == Some(&b':');
+                if is_key && &json[i + 1..end] == key {
+                    let value_start = skip_whitespace(after + 1);
+                    if bytes.get(value_start) != Some(&b'"') {
+                        return None;
+                    }
+                    let value_end = string_end(bytes, value_start)?;
+                    return Some(value_start + 1..value_end);
+                }
+                i = end;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// The index of the quote closing the JSON string that opens at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+impl fmt::Display for VersionFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            VersionFileKind::PackageManager => {
+                write!(f, "{} (packageManager)", self.path.display())
+            }
+            VersionFileKind::Engines => write!(f, "{} (engines.bun)", self.path.display()),
+            _ => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// The version file of `dir` that comes first in `LOOKUP_ORDER`. Each file
+/// is read at most once, and package.json is parsed only once for both of
+/// the places it can pin a version in, since the `bun` shim runs this on
+/// every invocation.
+fn read_version_file_in(dir: &Path) -> Option<VersionFile> {
+    let mut package: Option<Option<Value>> = None;
+
+    LOOKUP_ORDER.iter().find_map(|&kind| {
+        let path = dir.join(kind.file_name());
+        let version = if kind.reads_package_json() {
+            let package = package.get_or_insert_with(|| {
+                let content = fs::read_to_string(&path).ok()?;
+                serde_json::from_str(&content).ok()
+            });
+            kind.read_package_version(package.as_ref()?)
+        } else {
+            kind.read_version(&fs::read_to_string(&path).ok()?)
+        }?;
+
+        Some(VersionFile {
+            kind,
+            path,
+            version,
+        })
+    })
+}
+
+/// Looks for a pinned Bun version in `start` and then in each parent
+/// directory, checking the files of each directory in `LOOKUP_ORDER`.
+/// The search stops at the first directory containing `.git`, so a project
+/// never picks up a version from outside its repository, or at the
+/// filesystem root.
+pub fn find_version_file(start: &Path) -> Option<VersionFile> {
+    for dir in start.ancestors() {
+        if let Some(version_file) = read_version_file_in(dir) {
+            return Some(version_file);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn found_version(dir: &Path) -> Option<(VersionFileKind, String)> {
+        find_version_file(dir).map(|file| (file.kind, file.version))
+    }
+
+    #[test]
+    fn test_version_file_found_in_parent_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let nested = repo.join("packages").join("api").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::write(repo.join(".bumrc"), "1.1.20\n").unwrap();
+
+        let found = find_version_file(&nested).unwrap();
+        assert_eq!(found.path, repo.join(".bumrc"));
+        assert_eq!(found.version, "1.1.20");
+
+        // A nested project overrides the pin at the repository root
+        fs::write(repo.join("packages").join("api").join(".bumrc"), "1.0.0").unwrap();
+        assert_eq!(
+            find_version_file(&nested).unwrap().path,
+            repo.join("packages").join("api").join(".bumrc")
+        );
+    }
+
+    #[test]
+    fn test_search_stops_at_git_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let nested = repo.join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+
+        // Outside of the repository, so it must not be used
+        fs::write(temp_dir.path().join(".bumrc"), "1.1.20").unwrap();
+
+        assert_eq!(find_version_file(&nested), None);
+    }
+
+    #[test]
+    fn test_read_each_version_file_kind() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "name": "app", "engines": { "bun": ">=1.0.0 <2.0.0" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::Engines, ">=1.0.0 <2.0.0".to_string()))
+        );
+
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "packageManager": "bun@1.1.20+sha512.abc", "engines": { "bun": "^1.0.0" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::PackageManager, "1.1.20".to_string()))
+        );
+
+        fs::write(
+            dir.join(".tool-versions"),
+            "nodejs 20.11.0\nbun 1.1.18 # pinned for CI\n",
+        )
+        .unwrap();
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::ToolVersions, "1.1.18".to_string()))
+        );
+
+        fs::write(dir.join(".bun-version"), "v1.1.10\n").unwrap();
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::BunVersion, "v1.1.10".to_string()))
+        );
+
+        fs::write(dir.join(".bumrc"), "1.0.0\n").unwrap();
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::Bumrc, "1.0.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_write_version_keeps_rest_of_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+
+        fs::write(
+            dir.join("package.json"),
+            "{\n  \"name\": \"app\",\n  \"packageManager\": \"bun@1.0.0+sha512.abc\"\n}\n",
+        )
+        .unwrap();
+        find_version_file(dir)
+            .unwrap()
+            .write_version("1.1.20")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("package.json")).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"packageManager\": \"bun@1.1.20\"\n}\n"
+        );
+
+        fs::write(
+            dir.join(".tool-versions"),
+            "nodejs 20.11.0\nbun 1.0.0 # pinned for CI\n",
+        )
+        .unwrap();
+        find_version_file(dir)
+            .unwrap()
+            .write_version("1.1.20")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(".tool-versions")).unwrap(),
+            "nodejs 20.11.0\nbun 1.1.20 # pinned for CI\n"
+        );
+        assert_eq!(
+            found_version(dir),
+            Some((VersionFileKind::ToolVersions, "1.1.20".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_write_version_only_replaces_package_manager() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+
+        let package_json = r#"{
+  "scripts": { "setup": "npx bun@1.0.0 install", "packageManager": "bun@1.0.0" },
+  "dependencies": { "x": "npm:bun@1.0.0" },
+  "packageManager" : "bun@1.0.0"
+}
+"#;
+        fs::write(dir.join("package.json"), package_json).unwrap();
+        find_version_file(dir)
+            .unwrap()
+            .write_version("1.1.20")
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("package.json")).unwrap(),
+            package_json.replace(
+                r#""packageManager" : "bun@1.0.0""#,
+                r#""packageManager" : "bun@1.1.20""#
+            )
+        );
+    }
+
+    #[test]
+    fn test_files_without_bun_version_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        let app = repo.join("app");
+        fs::create_dir_all(&app).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+
+        fs::write(
+            app.join("package.json"),
+            r#"{ "packageManager": "pnpm@9.0.0" }"#,
+        )
+        .unwrap();
+        fs::write(app.join(".tool-versions"), "nodejs 20.11.0\n").unwrap();
+        fs::write(app.join(".bun-version"), "\n").unwrap();
+        fs::write(repo.join(".bun-version"), "1.1.20").unwrap();
+
+        assert_eq!(
+            found_version(&app),
+            Some((VersionFileKind::BunVersion, "1.1.20".to_string()))
+        );
+    }
+}